@@ -0,0 +1,525 @@
+//! Spatial anchors, fixed poses in the real world that content can be attached to.
+//!
+//! A [`XrTrackedAnchor`] is identified by a stable [`Uuid`] and is spawned as a child of the local [`XrOrigin`], so its transform is relative to the tracking space.
+//! Anchors are created with a [`XrCreateAnchor`] event, destroyed with a [`XrDestroyAnchor`] event and re-resolved by the xr platform specific crate with a [`XrLocateAnchor`] event whenever the runtime relocates them.
+//!
+//! Persistent anchors are saved to the [`XrAnchorStore`] of the [`XrAnchorStorage`] and restored in the next session, so content placed in an AR room can be restored.
+//! Restored anchors keep [`XrTrackingState::Lost`] until the backend locates them.
+//! By default the anchors are saved to a file with the [`XrFileAnchorStore`].
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{prelude::*, utils::Uuid};
+
+use crate::{space::XrOrigin, XrActive, XrLocal, XrSet, XrTrackingState};
+
+pub struct XrAnchorPlugin;
+
+impl Plugin for XrAnchorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrAnchorSettings>()
+            .init_resource::<XrAnchorStorage>()
+            .add_event::<XrCreateAnchor>()
+            .add_event::<XrDestroyAnchor>()
+            .add_event::<XrLocateAnchor>()
+            .add_systems(
+                PreUpdate,
+                (restore_anchors, apply_anchor_events)
+                    .chain()
+                    .after(XrSet::BackendSync)
+                    .before(XrSet::Derive),
+            )
+            .add_systems(Last, save_anchors);
+
+        app.register_type::<XrTrackedAnchor>()
+            .register_type::<XrAnchorSettings>()
+            .register_type::<XrCreateAnchor>()
+            .register_type::<XrDestroyAnchor>()
+            .register_type::<XrLocateAnchor>();
+    }
+}
+
+/// Settings of the [`XrAnchorPlugin`].
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Debug, Default)]
+pub struct XrAnchorSettings {
+    /// The minimum time in seconds between two saves of the persistent anchors, as relocated anchors change often.
+    pub save_interval: f32,
+}
+
+impl Default for XrAnchorSettings {
+    fn default() -> Self {
+        Self { save_interval: 1.0 }
+    }
+}
+
+/// The defining [`Component`] for spatial anchors.
+///
+/// Anchor entities are parented to the local [`XrOrigin`] and include a [`XrTrackingState`] and a [`XrActive`].
+/// Content placed relative to an anchor should be spawned as a child of it.
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrTrackedAnchor {
+    /// The identifier of the anchor which is stable across sessions.
+    pub id: Uuid,
+    /// Is the anchor saved to the [`XrAnchorStorage`]?
+    pub persistent: bool,
+}
+
+#[derive(Bundle)]
+pub struct XrTrackedAnchorBundle {
+    name: Name,
+    spatial_bundle: SpatialBundle,
+    xr_local: XrLocal,
+    xr_active: XrActive,
+    xr_tracking_state: XrTrackingState,
+    xr_tracked_anchor: XrTrackedAnchor,
+}
+
+impl XrTrackedAnchorBundle {
+    pub fn default(
+        anchor: XrTrackedAnchor,
+        transform: Transform,
+        tracking_state: XrTrackingState,
+    ) -> Self {
+        Self {
+            name: Name::new("XrTrackedAnchor_".to_string() + &anchor.id.to_string()),
+            spatial_bundle: SpatialBundle::from_transform(transform),
+            xr_local: XrLocal,
+            xr_active: tracking_state.into(),
+            xr_tracking_state: tracking_state,
+            xr_tracked_anchor: anchor,
+        }
+    }
+}
+
+/// An [`Event`] to create a [`XrTrackedAnchor`] at a pose in world space.
+#[derive(Event, Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrCreateAnchor {
+    pub id: Uuid,
+    /// The pose of the anchor in world space.
+    pub transform: Transform,
+    pub persistent: bool,
+}
+
+impl XrCreateAnchor {
+    /// Creates an anchor with a new random id, the id can be read from the event to refer to the anchor later.
+    pub fn new(transform: Transform, persistent: bool) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            transform,
+            persistent,
+        }
+    }
+}
+
+/// An [`Event`] to destroy a [`XrTrackedAnchor`] with its children and remove it from the [`XrAnchorStorage`].
+#[derive(Event, Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrDestroyAnchor {
+    pub id: Uuid,
+}
+
+/// An [`Event`] sent by the xr platform specific crate whenever the runtime locates or relocates a [`XrTrackedAnchor`].
+///
+/// Anchors which do not exist yet, e.g. anchors shared by another device, are spawned as non persistent anchors.
+#[derive(Event, Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrLocateAnchor {
+    pub id: Uuid,
+    /// The pose of the anchor relative to the [`XrOrigin`].
+    pub transform: Transform,
+    pub tracking_state: XrTrackingState,
+}
+
+/// An anchor as it is saved in a [`XrAnchorStore`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct XrPersistedAnchor {
+    pub id: Uuid,
+    /// The pose of the anchor relative to the [`XrOrigin`].
+    pub transform: Transform,
+}
+
+/// A storage of persistent anchors across sessions, e.g. a file or the anchor store of the runtime.
+pub trait XrAnchorStore: Send + Sync + 'static {
+    /// Loads all saved anchors.
+    fn load(&mut self) -> Result<Vec<XrPersistedAnchor>, XrAnchorStoreError>;
+
+    /// Replaces all saved anchors.
+    fn save(&mut self, anchors: &[XrPersistedAnchor]) -> Result<(), XrAnchorStoreError>;
+}
+
+/// An error while saving or loading anchors in a [`XrAnchorStore`].
+#[derive(Debug)]
+pub enum XrAnchorStoreError {
+    /// The store could not be read or written.
+    Io(io::Error),
+    /// The line of the file could not be parsed.
+    Parse { line: usize },
+    /// Any other error of a custom store.
+    Other(String),
+}
+
+impl fmt::Display for XrAnchorStoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XrAnchorStoreError::Io(error) => write!(f, "xr anchor store io error: {error}"),
+            XrAnchorStoreError::Parse { line } => {
+                write!(f, "xr anchor store parse error in line {line}")
+            }
+            XrAnchorStoreError::Other(error) => write!(f, "xr anchor store error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for XrAnchorStoreError {}
+
+impl From<io::Error> for XrAnchorStoreError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// A [`XrAnchorStore`] saving the anchors to a text file, one anchor per line with its id, translation and rotation.
+#[derive(Debug, Clone)]
+pub struct XrFileAnchorStore {
+    path: PathBuf,
+}
+
+impl XrFileAnchorStore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl XrAnchorStore for XrFileAnchorStore {
+    fn load(&mut self) -> Result<Vec<XrPersistedAnchor>, XrAnchorStoreError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+
+        contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                let parse_error = || XrAnchorStoreError::Parse { line: index + 1 };
+                let mut fields = line.split_whitespace();
+                let id = fields
+                    .next()
+                    .and_then(|id| Uuid::parse_str(id).ok())
+                    .ok_or_else(parse_error)?;
+                let values = fields
+                    .map(|value| value.parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| parse_error())?;
+                let [tx, ty, tz, rx, ry, rz, rw] = values[..] else {
+                    return Err(parse_error());
+                };
+                Ok(XrPersistedAnchor {
+                    id,
+                    transform: Transform::from_xyz(tx, ty, tz)
+                        .with_rotation(Quat::from_xyzw(rx, ry, rz, rw).normalize()),
+                })
+            })
+            .collect()
+    }
+
+    fn save(&mut self, anchors: &[XrPersistedAnchor]) -> Result<(), XrAnchorStoreError> {
+        let contents: String = anchors
+            .iter()
+            .map(|anchor| {
+                let translation = anchor.transform.translation;
+                let rotation = anchor.transform.rotation;
+                format!(
+                    "{} {} {} {} {} {} {} {}\n",
+                    anchor.id,
+                    translation.x,
+                    translation.y,
+                    translation.z,
+                    rotation.x,
+                    rotation.y,
+                    rotation.z,
+                    rotation.w
+                )
+            })
+            .collect();
+        fs::write(&self.path, contents)?;
+        Ok(())
+    }
+}
+
+/// This [`Resource`] holds the [`XrAnchorStore`] of the persistent anchors.
+///
+/// Defaults to a [`XrFileAnchorStore`] in `xr_anchors.txt`, insert another storage to change the store.
+#[derive(Resource)]
+pub struct XrAnchorStorage {
+    store: Box<dyn XrAnchorStore>,
+    restored: bool,
+    dirty: bool,
+    last_save: f32,
+}
+
+impl XrAnchorStorage {
+    pub fn new(store: impl XrAnchorStore) -> Self {
+        Self::boxed(Box::new(store))
+    }
+
+    pub fn boxed(store: Box<dyn XrAnchorStore>) -> Self {
+        Self {
+            store,
+            restored: false,
+            dirty: false,
+            last_save: f32::NEG_INFINITY,
+        }
+    }
+
+    /// Have the saved anchors been restored?
+    pub fn restored(&self) -> bool {
+        self.restored
+    }
+}
+
+impl Default for XrAnchorStorage {
+    fn default() -> Self {
+        Self::new(XrFileAnchorStore::new("xr_anchors.txt"))
+    }
+}
+
+/// Spawns the anchors saved in the [`XrAnchorStorage`] once the local [`XrOrigin`] exists.
+pub fn restore_anchors(
+    mut commands: Commands,
+    mut storage: ResMut<XrAnchorStorage>,
+    origins: Query<Entity, (With<XrOrigin>, With<XrLocal>)>,
+) {
+    if storage.restored {
+        return;
+    }
+    let Some(origin) = origins.iter().next() else {
+        return;
+    };
+    storage.restored = true;
+
+    let anchors = match storage.store.load() {
+        Ok(anchors) => anchors,
+        Err(error) => {
+            error!("Xr anchors could not be restored: {error}");
+            return;
+        }
+    };
+    for anchor in anchors {
+        commands
+            .spawn(XrTrackedAnchorBundle::default(
+                XrTrackedAnchor {
+                    id: anchor.id,
+                    persistent: true,
+                },
+                anchor.transform,
+                XrTrackingState::Lost,
+            ))
+            .set_parent(origin);
+    }
+}
+
+/// Creates, destroys and locates the [`XrTrackedAnchor`]s with the [`XrCreateAnchor`], [`XrDestroyAnchor`] and [`XrLocateAnchor`] events.
+#[allow(clippy::type_complexity)]
+pub fn apply_anchor_events(
+    mut commands: Commands,
+    origins: Query<(Entity, &GlobalTransform), (With<XrOrigin>, With<XrLocal>)>,
+    mut anchors: Query<(
+        Entity,
+        &XrTrackedAnchor,
+        &mut Transform,
+        &mut XrTrackingState,
+    )>,
+    mut create_events: EventReader<XrCreateAnchor>,
+    mut destroy_events: EventReader<XrDestroyAnchor>,
+    mut locate_events: EventReader<XrLocateAnchor>,
+) {
+    let origin = origins.iter().next();
+
+    for event in create_events.read() {
+        let Some((origin, origin_transform)) = origin else {
+            warn!(
+                "Xr anchor {} could not be created without an XrOrigin.",
+                event.id
+            );
+            continue;
+        };
+        let transform = GlobalTransform::from(event.transform)
+            .reparented_to(origin_transform)
+            .with_scale(Vec3::ONE);
+        commands
+            .spawn(XrTrackedAnchorBundle::default(
+                XrTrackedAnchor {
+                    id: event.id,
+                    persistent: event.persistent,
+                },
+                transform,
+                XrTrackingState::Tracked,
+            ))
+            .set_parent(origin);
+    }
+
+    for event in destroy_events.read() {
+        for (entity, anchor, _, _) in anchors.iter() {
+            if anchor.id == event.id {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+
+    for event in locate_events.read() {
+        let anchor = anchors
+            .iter_mut()
+            .find(|(_, anchor, _, _)| anchor.id == event.id);
+        if let Some((_, _, mut transform, mut tracking_state)) = anchor {
+            transform.set_if_neq(event.transform);
+            tracking_state.set_if_neq(event.tracking_state);
+        } else if let Some((origin, _)) = origin {
+            commands
+                .spawn(XrTrackedAnchorBundle::default(
+                    XrTrackedAnchor {
+                        id: event.id,
+                        persistent: false,
+                    },
+                    event.transform,
+                    event.tracking_state,
+                ))
+                .set_parent(origin);
+        }
+    }
+}
+
+/// Saves the persistent [`XrTrackedAnchor`]s to the [`XrAnchorStorage`] whenever they changed, at most once per [`XrAnchorSettings::save_interval`].
+#[allow(clippy::type_complexity)]
+pub fn save_anchors(
+    settings: Res<XrAnchorSettings>,
+    time: Res<Time>,
+    mut storage: ResMut<XrAnchorStorage>,
+    anchors: Query<(&XrTrackedAnchor, &Transform)>,
+    changed: Query<&XrTrackedAnchor, Or<(Changed<XrTrackedAnchor>, Changed<Transform>)>>,
+    mut removed: RemovedComponents<XrTrackedAnchor>,
+) {
+    // Removed anchors can not be checked for persistence anymore.
+    if removed.read().count() > 0 || changed.iter().any(|anchor| anchor.persistent) {
+        storage.dirty = true;
+    }
+    // Saving before the restore would overwrite the saved anchors.
+    if !storage.restored
+        || !storage.dirty
+        || time.elapsed_seconds() - storage.last_save < settings.save_interval
+    {
+        return;
+    }
+    storage.dirty = false;
+    storage.last_save = time.elapsed_seconds();
+
+    let persisted: Vec<XrPersistedAnchor> = anchors
+        .iter()
+        .filter(|(anchor, _)| anchor.persistent)
+        .map(|(anchor, transform)| XrPersistedAnchor {
+            id: anchor.id,
+            transform: *transform,
+        })
+        .collect();
+    if let Err(error) = storage.store.save(&persisted) {
+        error!("Xr anchors could not be saved: {error}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(name: &str) -> XrFileAnchorStore {
+        XrFileAnchorStore::new(
+            std::env::temp_dir().join(format!("bevy_xr_{name}_{}.txt", std::process::id())),
+        )
+    }
+
+    #[test]
+    fn file_store_round_trip() {
+        let mut store = store("round_trip");
+        let anchors = vec![
+            XrPersistedAnchor {
+                id: Uuid::from_u128(1),
+                transform: Transform::from_xyz(1.0, -2.5, 0.125)
+                    .with_rotation(Quat::from_rotation_y(0.7)),
+            },
+            XrPersistedAnchor {
+                id: Uuid::from_u128(u128::MAX),
+                transform: Transform::IDENTITY,
+            },
+        ];
+        store.save(&anchors).unwrap();
+        let loaded = store.load().unwrap();
+        fs::remove_file(store.path()).unwrap();
+
+        assert_eq!(loaded.len(), anchors.len());
+        for (loaded, anchor) in loaded.iter().zip(anchors.iter()) {
+            assert_eq!(loaded.id, anchor.id);
+            assert_eq!(loaded.transform.translation, anchor.transform.translation);
+            assert!(loaded
+                .transform
+                .rotation
+                .abs_diff_eq(anchor.transform.rotation, 1e-6));
+        }
+        assert!(store.load().unwrap().is_empty());
+    }
+
+    #[test]
+    fn file_store_parse_errors() {
+        let mut store = store("parse_errors");
+        let id = Uuid::from_u128(7);
+        for (contents, error_line) in [
+            (
+                format!("\n{id} 0 0 0 0 0 0 1\nnot-a-uuid 0 0 0 0 0 0 1\n"),
+                3,
+            ),
+            (format!("{id} 0 0 0 0 0 1\n"), 1),
+            (format!("{id} 0 0 0 0 0 0 1 0\n"), 1),
+            (format!("{id} 0 zero 0 0 0 0 1\n"), 1),
+        ] {
+            fs::write(store.path(), contents).unwrap();
+            let result = store.load();
+            assert!(
+                matches!(result, Err(XrAnchorStoreError::Parse { line }) if line == error_line),
+                "{result:?}"
+            );
+        }
+        fs::remove_file(store.path()).unwrap();
+    }
+}
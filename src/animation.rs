@@ -0,0 +1,195 @@
+//! Conversion of a [`XrRecording`] into an [`AnimationClip`].
+//!
+//! The curves of the clip target entities by their [`Name`], e.g. `XrHand_LeftHandedIndexTip`, which allows replaying a recorded performance on any hierarchy with the same names such as an avatar.
+//!
+//! Requires the `recording` feature.
+
+use std::ops::Range;
+
+use bevy::{
+    animation::{EntityPath, Keyframes, VariableCurve},
+    prelude::*,
+    utils::HashMap,
+};
+
+use crate::recording::XrRecording;
+
+/// The keyframes of a single recorded entity.
+#[derive(Default)]
+struct PoseCurves {
+    timestamps: Vec<f32>,
+    translations: Vec<Vec3>,
+    rotations: Vec<Quat>,
+}
+
+impl XrRecording {
+    /// Creates an [`AnimationClip`] of the recorded poses within the time `range` in seconds.
+    ///
+    /// The `paths` map the recorded names to the [`EntityPath`]s of the animated hierarchy, see [`animation_paths`].
+    /// Recorded entities without a path are skipped. Poses of frames in which an entity was not active are skipped, the clip interpolates over the tracking loss.
+    ///
+    /// The keyframes of the clip start at zero.
+    pub fn to_animation_clip(
+        &self,
+        range: Range<f32>,
+        paths: &HashMap<String, EntityPath>,
+    ) -> AnimationClip {
+        let mut curves: HashMap<&str, PoseCurves> = HashMap::default();
+
+        for frame in self
+            .frames
+            .iter()
+            .filter(|frame| range.contains(&frame.time))
+        {
+            for pose in frame.poses.iter() {
+                if !pose.tracking_state.is_active() || !paths.contains_key(&pose.name) {
+                    continue;
+                }
+                let curve = curves.entry(pose.name.as_str()).or_default();
+                curve.timestamps.push(frame.time - range.start);
+                curve.translations.push(pose.transform.translation);
+                curve.rotations.push(pose.transform.rotation);
+            }
+        }
+
+        let mut clip = AnimationClip::default();
+        for (name, curve) in curves {
+            let path = &paths[name];
+            clip.add_curve_to_path(
+                path.clone(),
+                VariableCurve {
+                    keyframe_timestamps: curve.timestamps.clone(),
+                    keyframes: Keyframes::Translation(curve.translations),
+                },
+            );
+            clip.add_curve_to_path(
+                path.clone(),
+                VariableCurve {
+                    keyframe_timestamps: curve.timestamps,
+                    keyframes: Keyframes::Rotation(curve.rotations),
+                },
+            );
+        }
+        clip
+    }
+}
+
+/// Returns the [`EntityPath`] of every named entity in the hierarchy below and including the `root`, keyed by the name of the entity.
+///
+/// The `root` should be the entity with the [`AnimationPlayer`] the clip is played on.
+pub fn animation_paths(
+    root: Entity,
+    names: &Query<&Name>,
+    children: &Query<&Children>,
+) -> HashMap<String, EntityPath> {
+    let mut paths = HashMap::default();
+    let mut stack = vec![(root, Vec::new())];
+
+    while let Some((entity, mut parts)) = stack.pop() {
+        let Ok(name) = names.get(entity) else {
+            continue;
+        };
+        parts.push(name.clone());
+        paths.insert(
+            name.to_string(),
+            EntityPath {
+                parts: parts.clone(),
+            },
+        );
+        if let Ok(children) = children.get(entity) {
+            for child in children.iter() {
+                stack.push((*child, parts.clone()));
+            }
+        }
+    }
+
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+
+    use super::*;
+    use crate::{
+        recording::{XrRecordedFrame, XrRecordedPose},
+        XrTrackingState,
+    };
+
+    fn pose(name: &str, x: f32, tracking_state: XrTrackingState) -> XrRecordedPose {
+        XrRecordedPose {
+            name: name.to_string(),
+            transform: Transform::from_xyz(x, 0.0, 0.0),
+            tracking_state,
+        }
+    }
+
+    #[test]
+    fn animation_clip_of_range() {
+        let recording = XrRecording {
+            frames: [0.0, 0.5, 1.0, 1.5]
+                .into_iter()
+                .enumerate()
+                .map(|(i, time)| XrRecordedFrame {
+                    time,
+                    poses: vec![
+                        pose("XrHeadset", i as f32, XrTrackingState::Tracked),
+                        pose(
+                            "XrHand",
+                            i as f32,
+                            match i {
+                                2 => XrTrackingState::Lost,
+                                _ => XrTrackingState::Tracked,
+                            },
+                        ),
+                        pose("Unmapped", i as f32, XrTrackingState::Tracked),
+                    ],
+                    ..default()
+                })
+                .collect(),
+        };
+
+        let mut world = World::new();
+        let hand = world.spawn(Name::new("XrHand")).id();
+        let headset = world
+            .spawn(Name::new("XrHeadset"))
+            .push_children(&[hand])
+            .id();
+        let root = world
+            .spawn(Name::new("Avatar"))
+            .push_children(&[headset])
+            .id();
+        let mut state: SystemState<(Query<&Name>, Query<&Children>)> = SystemState::new(&mut world);
+        let (names, children) = state.get(&world);
+        let paths = animation_paths(root, &names, &children);
+
+        let clip = recording.to_animation_clip(0.5..2.0, &paths);
+        assert_eq!(clip.curves().len(), 2);
+
+        let path = |names: &[&str]| EntityPath {
+            parts: names
+                .iter()
+                .map(|name| Name::new(name.to_string()))
+                .collect(),
+        };
+        let headset_curves = clip
+            .get_curves_by_path(&path(&["Avatar", "XrHeadset"]))
+            .unwrap();
+        assert_eq!(headset_curves.len(), 2);
+        for curve in headset_curves {
+            assert_eq!(curve.keyframe_timestamps, vec![0.0, 0.5, 1.0]);
+        }
+        match &headset_curves[0].keyframes {
+            Keyframes::Translation(translations) => {
+                assert_eq!(translations, &vec![Vec3::X, Vec3::X * 2.0, Vec3::X * 3.0])
+            }
+            _ => panic!("the first curve should be the translation"),
+        }
+
+        // The frame with the lost hand is skipped.
+        let hand_curves = clip
+            .get_curves_by_path(&path(&["Avatar", "XrHeadset", "XrHand"]))
+            .unwrap();
+        assert_eq!(hand_curves[0].keyframe_timestamps, vec![0.0, 1.0]);
+    }
+}
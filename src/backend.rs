@@ -0,0 +1,574 @@
+//! The contract between this crate and the xr platform specific crates.
+//!
+//! A platform crate implements the [`XrBackend`] trait with the glue to its device or runtime, e.g. openxr or webxr.
+//! The [`XrRuntimePlugin`] owns the ECS side of the contract: it spawns the rig described by the backend with the bundles of this crate,
+//! applies the poses the backend submits by [`XrRigPart`] in [`XrSet::BackendSync`] and sends the input and session events of the backend.
+//!
+//! As the backend is stored as a trait object the app can choose the backend at startup:
+//!
+//! ```ignore
+//! let backend: Box<dyn XrBackend> = if use_webxr { Box::new(WebXrBackend::new()) } else { Box::new(OpenXrBackend::new()) };
+//! app.add_plugins((XrPlugins, XrRuntimePlugin::boxed(backend)));
+//! ```
+
+use std::sync::Mutex;
+
+use bevy::{
+    hierarchy::WorldChildBuilder,
+    prelude::*,
+    utils::{HashMap, HashSet, Uuid},
+};
+
+use crate::{
+    anchor::XrLocateAnchor,
+    controller::{XrController, XrControllerBundle, XrControllerHandlessBundle},
+    controller_input::XrControllerEvent,
+    gaze::XrEyeGaze,
+    handedness::{Handedness, HandednessMarker, LeftHanded, RightHanded},
+    hands::{
+        finger::{FingerMarker, Index, Little, Middle, Ring, Thumb},
+        finger_joint::{DistalPhalanx, IntermediatePhalanx, Metacarpal, ProximalPhalanx, Tip},
+        hand_joint::{Forearm, HandJointMarker, Palm, Wrist},
+        FingerJointBundle, Hand, HandJointBundle,
+    },
+    head::{XrEyeBundle, XrHead, XrHeadsetBundle},
+    image::{XrImageTarget, XrLocateImage},
+    session::XrSessionEvent,
+    space::{XrOrigin, XrOriginBundle},
+    tracked::XrTrackedObjectBundle,
+    window::XrWindowBundle,
+    IntoEnum, XrActive, XrLocal, XrSet, XrTrackingState, XrView,
+};
+
+/// The device glue of a xr platform specific crate.
+///
+/// The backend describes the rig and the capabilities of the device and reports the poses and events of each frame into a [`XrBackendFrame`].
+/// Everything else is done by the [`XrRuntimePlugin`].
+pub trait XrBackend: Send + Sync + 'static {
+    /// The name of the backend, e.g. `"openxr"`.
+    fn name(&self) -> &str;
+
+    /// The capabilities of the device.
+    fn capabilities(&self) -> XrCapabilities;
+
+    /// The rig spawned by the [`XrRuntimePlugin`] at [`Startup`].
+    fn rig(&self) -> XrRigDescriptor;
+
+    /// Polls the device and reports the poses and events of the frame.
+    ///
+    /// Called once per frame in [`XrSet::BackendSync`]. The `world` can be read, e.g. for the [`Time`] or the entities of the [`XrRuntime`].
+    fn update(&mut self, world: &World, frame: &mut XrBackendFrame);
+}
+
+/// The capabilities of a [`XrBackend`].
+///
+/// This [`Resource`] is inserted by the [`XrRuntimePlugin`] and allows apps to adapt to the device, e.g. hiding hand interactions without hand tracking.
+#[derive(Resource, Debug, Default, Clone, PartialEq, Reflect)]
+#[reflect(Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrCapabilities {
+    /// The device supports [`crate::XrMode::VR`].
+    pub vr: bool,
+    /// The device supports [`crate::XrMode::AR`].
+    pub ar: bool,
+    /// The device reports controllers.
+    pub controllers: bool,
+    /// The device reports the joints of the hands.
+    pub hand_tracking: bool,
+    /// The device reports the gaze of the eyes.
+    pub eye_tracking: bool,
+}
+
+/// A part of the rig identified independently of its entity.
+///
+/// A [`XrBackend`] submits its poses by this id.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrRigPart {
+    Headset,
+    Head,
+    /// An eye of the view pair with the index.
+    Eye(Handedness, u8),
+    Window(u8),
+    Controller(XrController),
+    TrackedObject(u8),
+    HandJoint(Handedness, Hand),
+}
+
+/// The description of the rig spawned for a [`XrBackend`].
+///
+/// All parts are spawned as children of the [`XrOrigin`] and their poses are submitted relative to it.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+#[reflect(Debug, Default, PartialEq)]
+pub struct XrRigDescriptor {
+    /// The kind of the [`XrOrigin`].
+    pub origin: XrOrigin,
+    /// Spawns a [`XrHead`] besides the headset.
+    pub head: bool,
+    /// The amount of stereo view pairs, each pair spawns a left and a right [`crate::head::XrEye`].
+    pub eyes: u8,
+    /// The amount of [`crate::window::XrWindow`]s, e.g. the screen of a smartphone.
+    ///
+    /// The [`XrView`] indices of the windows follow the views of the eyes.
+    pub windows: u8,
+    /// The controllers of the device. Left and right controllers are spawned with their handedness.
+    pub controllers: Vec<XrController>,
+    /// Spawns the joints of both hands.
+    pub hands: bool,
+    /// The amount of additional [`crate::tracked::XrTrackedObject`]s, e.g. pucks.
+    pub tracked_objects: u8,
+}
+
+impl Default for XrRigDescriptor {
+    fn default() -> Self {
+        Self {
+            origin: XrOrigin::Room,
+            head: false,
+            eyes: 1,
+            windows: 0,
+            controllers: vec![XrController::Left, XrController::Right],
+            hands: false,
+            tracked_objects: 0,
+        }
+    }
+}
+
+/// A pose submitted to a [`XrBackendFrame`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct XrBackendPose {
+    pub part: XrRigPart,
+    /// The transform relative to the [`XrOrigin`], [`None`] keeps the last transform.
+    pub transform: Option<Transform>,
+    pub tracking_state: XrTrackingState,
+}
+
+/// The poses and events a [`XrBackend`] reports for a single frame.
+#[derive(Debug, Default, Clone)]
+pub struct XrBackendFrame {
+    poses: Vec<XrBackendPose>,
+    controller_events: Vec<XrControllerEvent>,
+    session_events: Vec<XrSessionEvent>,
+    anchor_events: Vec<XrLocateAnchor>,
+    image_events: Vec<XrLocateImage>,
+    eye_gaze: Option<XrEyeGaze>,
+}
+
+impl XrBackendFrame {
+    /// Submits the pose of a part relative to the [`XrOrigin`].
+    pub fn submit_pose(
+        &mut self,
+        part: XrRigPart,
+        transform: Transform,
+        tracking_state: XrTrackingState,
+    ) {
+        self.poses.push(XrBackendPose {
+            part,
+            transform: Some(transform),
+            tracking_state,
+        });
+    }
+
+    /// Changes the tracking state of a part without changing its pose, e.g. when the tracking is lost.
+    pub fn set_tracking_state(&mut self, part: XrRigPart, tracking_state: XrTrackingState) {
+        self.poses.push(XrBackendPose {
+            part,
+            transform: None,
+            tracking_state,
+        });
+    }
+
+    /// Sends a controller event, see [`XrControllerEvent`].
+    pub fn send_controller_event(&mut self, event: impl Into<XrControllerEvent>) {
+        self.controller_events.push(event.into());
+    }
+
+    /// Sends a session event, see [`XrSessionEvent`].
+    pub fn send_session_event(&mut self, event: impl Into<XrSessionEvent>) {
+        self.session_events.push(event.into());
+    }
+
+    /// Locates or relocates an anchor relative to the [`XrOrigin`], see [`XrLocateAnchor`].
+    ///
+    /// The events are only sent if the [`crate::anchor::XrAnchorPlugin`] is added.
+    pub fn locate_anchor(
+        &mut self,
+        id: Uuid,
+        transform: Transform,
+        tracking_state: XrTrackingState,
+    ) {
+        self.anchor_events.push(XrLocateAnchor {
+            id,
+            transform,
+            tracking_state,
+        });
+    }
+
+    /// Locates a detected instance of an image or a marker relative to the [`XrOrigin`], see [`XrLocateImage`].
+    ///
+    /// The events are only sent if the [`crate::image::XrImagePlugin`] is added.
+    pub fn locate_image(
+        &mut self,
+        instance: u64,
+        target: XrImageTarget,
+        transform: Transform,
+        tracking_state: XrTrackingState,
+    ) {
+        self.image_events.push(XrLocateImage {
+            instance,
+            target,
+            transform,
+            tracking_state,
+        });
+    }
+
+    /// Submits the eye gaze of the frame relative to the [`XrOrigin`], see [`XrEyeGaze`].
+    ///
+    /// The gaze is inserted on the [`XrOrigin`] of the rig, the last submitted gaze of a frame wins.
+    pub fn submit_eye_gaze(&mut self, gaze: XrEyeGaze) {
+        self.eye_gaze = Some(gaze);
+    }
+
+    /// The submitted poses in the order they were submitted.
+    pub fn poses(&self) -> &[XrBackendPose] {
+        &self.poses
+    }
+
+    /// The sent controller events in the order they were sent.
+    pub fn controller_events(&self) -> &[XrControllerEvent] {
+        &self.controller_events
+    }
+
+    /// The sent session events in the order they were sent.
+    pub fn session_events(&self) -> &[XrSessionEvent] {
+        &self.session_events
+    }
+
+    /// The located anchors in the order they were located.
+    pub fn anchor_events(&self) -> &[XrLocateAnchor] {
+        &self.anchor_events
+    }
+
+    /// The located images and markers in the order they were located.
+    pub fn image_events(&self) -> &[XrLocateImage] {
+        &self.image_events
+    }
+
+    /// The submitted eye gaze.
+    pub fn eye_gaze(&self) -> Option<&XrEyeGaze> {
+        self.eye_gaze.as_ref()
+    }
+}
+
+/// Adds a [`XrBackend`] to the app. Requires the [`crate::XrPlugins`].
+pub struct XrRuntimePlugin {
+    backend: Mutex<Option<Box<dyn XrBackend>>>,
+}
+
+impl XrRuntimePlugin {
+    pub fn new(backend: impl XrBackend) -> Self {
+        Self::boxed(Box::new(backend))
+    }
+
+    /// Creates the plugin from a backend chosen at runtime.
+    pub fn boxed(backend: Box<dyn XrBackend>) -> Self {
+        Self {
+            backend: Mutex::new(Some(backend)),
+        }
+    }
+}
+
+impl Plugin for XrRuntimePlugin {
+    fn build(&self, app: &mut App) {
+        let backend = self
+            .backend
+            .lock()
+            .unwrap()
+            .take()
+            .expect("the XrRuntimePlugin can only be built once");
+
+        app.insert_resource(backend.capabilities())
+            .insert_resource(XrRuntime {
+                backend,
+                origin: None,
+                parts: HashMap::default(),
+                unknown_parts: HashSet::default(),
+            })
+            .add_systems(Startup, spawn_backend_rig)
+            .add_systems(PreUpdate, sync_backend.in_set(XrSet::BackendSync));
+
+        app.register_type::<XrCapabilities>()
+            .register_type::<XrRigPart>()
+            .register_type::<XrRigDescriptor>();
+    }
+}
+
+/// This [`Resource`] owns the [`XrBackend`] and the entities of its rig.
+#[derive(Resource)]
+pub struct XrRuntime {
+    backend: Box<dyn XrBackend>,
+    origin: Option<Entity>,
+    parts: HashMap<XrRigPart, Entity>,
+    unknown_parts: HashSet<XrRigPart>,
+}
+
+impl XrRuntime {
+    /// The name of the backend.
+    pub fn name(&self) -> &str {
+        self.backend.name()
+    }
+
+    /// The [`XrOrigin`] of the rig, [`None`] before the rig is spawned.
+    pub fn origin(&self) -> Option<Entity> {
+        self.origin
+    }
+
+    /// The entity of a part of the rig.
+    pub fn entity(&self, part: XrRigPart) -> Option<Entity> {
+        self.parts.get(&part).copied()
+    }
+
+    /// All parts of the rig and their entities.
+    pub fn parts(&self) -> impl Iterator<Item = (XrRigPart, Entity)> + '_ {
+        self.parts.iter().map(|(part, entity)| (*part, *entity))
+    }
+}
+
+/// Spawns the rig described by the [`XrBackend`] of the [`XrRuntime`].
+pub fn spawn_backend_rig(world: &mut World) {
+    world.resource_scope(|world, mut runtime: Mut<XrRuntime>| {
+        let rig = runtime.backend.rig();
+        let mut parts = HashMap::default();
+
+        let origin = world
+            .spawn(XrOriginBundle::default(rig.origin))
+            .with_children(|origin| {
+                spawn_part(
+                    origin,
+                    &mut parts,
+                    XrRigPart::Headset,
+                    XrHeadsetBundle::default(),
+                );
+                if rig.head {
+                    spawn_part(
+                        origin,
+                        &mut parts,
+                        XrRigPart::Head,
+                        (
+                            Name::new("XrHead"),
+                            SpatialBundle::default(),
+                            XrHead,
+                            XrLocal,
+                            XrActive(true),
+                            XrTrackingState::Tracked,
+                        ),
+                    );
+                }
+                for index in 0..rig.eyes {
+                    for handedness in [Handedness::Left, Handedness::Right] {
+                        let Some(view) = XrView::eye(index, handedness) else {
+                            error!("the view of the eye {index} exceeds the XrView index range");
+                            continue;
+                        };
+                        let mut eye = match handedness {
+                            Handedness::Left => {
+                                origin.spawn(XrEyeBundle::<LeftHanded>::default(index, view))
+                            }
+                            Handedness::Right => {
+                                origin.spawn(XrEyeBundle::<RightHanded>::default(index, view))
+                            }
+                        };
+                        eye.insert(Camera {
+                            order: view.0 as isize,
+                            ..default()
+                        });
+                        parts.insert(XrRigPart::Eye(handedness, index), eye.id());
+                    }
+                }
+                for index in 0..rig.windows {
+                    let Some(view) = XrView::window(rig.eyes, index) else {
+                        error!("the view of the window {index} exceeds the XrView index range");
+                        continue;
+                    };
+                    let window = origin
+                        .spawn(XrWindowBundle::default(index, view))
+                        .insert(Camera {
+                            order: view.0 as isize,
+                            ..default()
+                        })
+                        .id();
+                    parts.insert(XrRigPart::Window(index), window);
+                }
+
+                let mut tracked_index = 0;
+                for xr_controller in rig.controllers.iter() {
+                    let part = XrRigPart::Controller(*xr_controller);
+                    match xr_controller {
+                        XrController::Left => spawn_part(
+                            origin,
+                            &mut parts,
+                            part,
+                            XrControllerBundle::<LeftHanded>::default(tracked_index),
+                        ),
+                        XrController::Right => spawn_part(
+                            origin,
+                            &mut parts,
+                            part,
+                            XrControllerBundle::<RightHanded>::default(tracked_index),
+                        ),
+                        XrController::Other(index) => spawn_part(
+                            origin,
+                            &mut parts,
+                            part,
+                            XrControllerHandlessBundle::default(*index),
+                        ),
+                    }
+                    tracked_index += 1;
+                }
+                for index in 0..rig.tracked_objects {
+                    spawn_part(
+                        origin,
+                        &mut parts,
+                        XrRigPart::TrackedObject(index),
+                        XrTrackedObjectBundle::default(tracked_index + index),
+                    );
+                }
+
+                if rig.hands {
+                    spawn_hand::<LeftHanded>(origin, &mut parts);
+                    spawn_hand::<RightHanded>(origin, &mut parts);
+                }
+            })
+            .id();
+
+        info!(
+            "Spawned the xr rig of the {} backend with {} parts.",
+            runtime.name(),
+            parts.len()
+        );
+        runtime.origin = Some(origin);
+        runtime.parts = parts;
+    });
+}
+
+fn spawn_part(
+    origin: &mut WorldChildBuilder,
+    parts: &mut HashMap<XrRigPart, Entity>,
+    part: XrRigPart,
+    bundle: impl Bundle,
+) {
+    parts.insert(part, origin.spawn(bundle).id());
+}
+
+fn spawn_hand<Handed: HandednessMarker>(
+    origin: &mut WorldChildBuilder,
+    parts: &mut HashMap<XrRigPart, Entity>,
+) {
+    spawn_hand_joint::<Handed, Forearm>(origin, parts);
+    spawn_hand_joint::<Handed, Wrist>(origin, parts);
+    spawn_hand_joint::<Handed, Palm>(origin, parts);
+    spawn_finger_joint::<Handed, Thumb, Metacarpal>(origin, parts);
+    spawn_finger_joint::<Handed, Thumb, ProximalPhalanx>(origin, parts);
+    spawn_finger_joint::<Handed, Thumb, DistalPhalanx>(origin, parts);
+    spawn_finger_joint::<Handed, Thumb, Tip>(origin, parts);
+    spawn_finger::<Handed, Index>(origin, parts);
+    spawn_finger::<Handed, Middle>(origin, parts);
+    spawn_finger::<Handed, Ring>(origin, parts);
+    spawn_finger::<Handed, Little>(origin, parts);
+}
+
+fn spawn_finger<Handed: HandednessMarker, Finger: FingerMarker>(
+    origin: &mut WorldChildBuilder,
+    parts: &mut HashMap<XrRigPart, Entity>,
+) where
+    (Finger, Metacarpal): IntoEnum<Hand>,
+    (Finger, ProximalPhalanx): IntoEnum<Hand>,
+    (Finger, IntermediatePhalanx): IntoEnum<Hand>,
+    (Finger, DistalPhalanx): IntoEnum<Hand>,
+    (Finger, Tip): IntoEnum<Hand>,
+{
+    spawn_finger_joint::<Handed, Finger, Metacarpal>(origin, parts);
+    spawn_finger_joint::<Handed, Finger, ProximalPhalanx>(origin, parts);
+    spawn_finger_joint::<Handed, Finger, IntermediatePhalanx>(origin, parts);
+    spawn_finger_joint::<Handed, Finger, DistalPhalanx>(origin, parts);
+    spawn_finger_joint::<Handed, Finger, Tip>(origin, parts);
+}
+
+fn spawn_hand_joint<Handed: HandednessMarker, Joint: HandJointMarker>(
+    origin: &mut WorldChildBuilder,
+    parts: &mut HashMap<XrRigPart, Entity>,
+) {
+    let bundle = HandJointBundle::<Handed, Joint>::default();
+    let part = XrRigPart::HandJoint(bundle.handedness_enum, bundle.hand);
+    spawn_part(origin, parts, part, bundle);
+}
+
+fn spawn_finger_joint<
+    Handed: HandednessMarker,
+    Finger: FingerMarker,
+    Joint: crate::hands::finger_joint::FingerJointMarker,
+>(
+    origin: &mut WorldChildBuilder,
+    parts: &mut HashMap<XrRigPart, Entity>,
+) where
+    (Finger, Joint): IntoEnum<Hand>,
+{
+    let bundle = FingerJointBundle::<Handed, Finger, Joint>::default();
+    let part = XrRigPart::HandJoint(bundle.handedness_enum, bundle.hand);
+    spawn_part(origin, parts, part, bundle);
+}
+
+/// Updates the [`XrBackend`] of the [`XrRuntime`] and applies its [`XrBackendFrame`] to the rig.
+pub fn sync_backend(world: &mut World) {
+    world.resource_scope(|world, mut runtime: Mut<XrRuntime>| {
+        let mut frame = XrBackendFrame::default();
+        runtime.backend.update(world, &mut frame);
+
+        for pose in frame.poses {
+            let entity = runtime.parts.get(&pose.part).copied();
+            let Some(mut entity) = entity.and_then(|entity| world.get_entity_mut(entity)) else {
+                if runtime.unknown_parts.insert(pose.part) {
+                    warn!(
+                        "The {} backend submitted a pose for {:?} which is not part of the rig.",
+                        runtime.name(),
+                        pose.part
+                    );
+                }
+                continue;
+            };
+            if let Some(transform) = pose.transform {
+                if let Some(mut current) = entity.get_mut::<Transform>() {
+                    *current = transform;
+                }
+            }
+            if let Some(mut tracking_state) = entity.get_mut::<XrTrackingState>() {
+                tracking_state.set_if_neq(pose.tracking_state);
+            }
+        }
+
+        world.send_event_batch(frame.session_events);
+        world.send_event_batch(frame.controller_events);
+        if world.contains_resource::<Events<XrLocateAnchor>>() {
+            world.send_event_batch(frame.anchor_events);
+        }
+        if world.contains_resource::<Events<XrLocateImage>>() {
+            world.send_event_batch(frame.image_events);
+        }
+        if let Some(gaze) = frame.eye_gaze {
+            if let Some(mut origin) = runtime
+                .origin
+                .and_then(|origin| world.get_entity_mut(origin))
+            {
+                origin.insert(gaze);
+            }
+        }
+    });
+}
@@ -0,0 +1,447 @@
+//! The boundary of the play area, also known as chaperone or guardian.
+//!
+//! A [`XrBoundary`] holds the polygon of the play area on the floor of its entity, which is usually spawned as a child of the [`crate::space::XrOrigin`] by the xr platform specific crate.
+//! The [`XrBoundaryPlugin`] sends a [`XrBoundaryProximity`] event whenever the head, a controller or a hand comes close to or crosses the boundary, so apps can warn users before they walk into walls.
+//!
+//! The boundary is drawn by the [`crate::plugin::XrGizmoPlugin`] and [`XrBoundary::wall_mesh`] creates a [`Mesh`] for a custom visualization.
+
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    transform::TransformSystem,
+    utils::HashMap,
+};
+
+use crate::{
+    controller::XrController,
+    hands::Hand,
+    head::{XrHead, XrHeadset},
+    XrActive, XrLocal,
+};
+
+pub struct XrBoundaryPlugin;
+
+impl Plugin for XrBoundaryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrBoundarySettings>()
+            .add_event::<XrBoundaryProximity>()
+            .add_systems(
+                PostUpdate,
+                send_boundary_proximity.after(TransformSystem::TransformPropagate),
+            );
+
+        app.register_type::<XrBoundary>()
+            .register_type::<XrBoundarySettings>()
+            .register_type::<XrBoundaryZone>()
+            .register_type::<XrBoundaryProximity>();
+    }
+}
+
+/// Settings of the [`XrBoundaryPlugin`].
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Debug, Default)]
+pub struct XrBoundarySettings {
+    /// The distance to the boundary in meters below which an entity is [`XrBoundaryZone::Near`].
+    pub warning_distance: f32,
+    /// The joints of the hands which are tracked, by default the wrists and the finger tips.
+    pub hand_joints: Vec<Hand>,
+}
+
+impl Default for XrBoundarySettings {
+    fn default() -> Self {
+        Self {
+            warning_distance: 0.4,
+            hand_joints: vec![
+                Hand::Wrist,
+                Hand::ThumbTip,
+                Hand::IndexTip,
+                Hand::MiddleTip,
+                Hand::RingTip,
+                Hand::LittleTip,
+            ],
+        }
+    }
+}
+
+/// The defining [`Component`] for the boundary of the play area.
+/// Represents a polygon on the floor of the entity, the `x` and `y` of each point are the `x` and `z` coordinates relative to the entity.
+///
+/// The polygon may be concave and is closed implicitly, the last point connects to the first.
+///
+/// Boundary entities should be parented to a [`crate::space::XrOrigin`] entity or be the origin itself.
+#[derive(Component, Debug, Default, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrBoundary {
+    pub points: Vec<Vec2>,
+}
+
+/// The edge of a [`XrBoundary`] nearest to a point.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct XrBoundaryEdge {
+    /// The index of the start point, the edge ends at the next point.
+    pub index: usize,
+    pub start: Vec2,
+    pub end: Vec2,
+    /// The point on the edge nearest to the queried point.
+    pub closest: Vec2,
+    /// The distance of the queried point to the edge.
+    pub distance: f32,
+}
+
+impl XrBoundary {
+    pub fn new(points: Vec<Vec2>) -> Self {
+        Self { points }
+    }
+
+    /// Creates a rectangular boundary with the `size` in meters centered at the entity.
+    pub fn rectangle(size: Vec2) -> Self {
+        let half = size / 2.0;
+        Self::new(vec![
+            Vec2::new(-half.x, -half.y),
+            Vec2::new(half.x, -half.y),
+            Vec2::new(half.x, half.y),
+            Vec2::new(-half.x, half.y),
+        ])
+    }
+
+    /// Iterates over the edges as start and end points.
+    pub fn edges(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        self.points
+            .iter()
+            .zip(self.points.iter().cycle().skip(1))
+            .map(|(start, end)| (*start, *end))
+    }
+
+    /// Projects a point in world space onto the floor of the boundary entity with the `transform`.
+    pub fn project(transform: &GlobalTransform, point: Vec3) -> Vec2 {
+        let local = transform.affine().inverse().transform_point3(point);
+        Vec2::new(local.x, local.z)
+    }
+
+    /// Is the point inside of the polygon?
+    pub fn contains(&self, point: Vec2) -> bool {
+        let mut inside = false;
+        for (start, end) in self.edges() {
+            if (start.y > point.y) != (end.y > point.y)
+                && point.x < start.x + (point.y - start.y) / (end.y - start.y) * (end.x - start.x)
+            {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    /// Returns the edge nearest to the point, [`None`] for a boundary without points.
+    pub fn nearest_edge(&self, point: Vec2) -> Option<XrBoundaryEdge> {
+        self.edges()
+            .enumerate()
+            .map(|(index, (start, end))| {
+                let edge = end - start;
+                let t = if edge.length_squared() > 0.0 {
+                    ((point - start).dot(edge) / edge.length_squared()).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let closest = start + edge * t;
+                XrBoundaryEdge {
+                    index,
+                    start,
+                    end,
+                    closest,
+                    distance: point.distance(closest),
+                }
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+
+    /// The distance of the point to the nearest edge, positive inside and negative outside of the polygon.
+    ///
+    /// Returns negative infinity for a boundary without points.
+    pub fn signed_distance(&self, point: Vec2) -> f32 {
+        let Some(edge) = self.nearest_edge(point) else {
+            return f32::NEG_INFINITY;
+        };
+        if self.contains(point) {
+            edge.distance
+        } else {
+            -edge.distance
+        }
+    }
+
+    /// Creates a [`Mesh`] of walls with the `height` along the edges, facing into the play area.
+    pub fn wall_mesh(&self, height: f32) -> Mesh {
+        let area: f32 = self
+            .edges()
+            .map(|(start, end)| start.x * end.y - end.x * start.y)
+            .sum();
+
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        let mut indices = Vec::new();
+        let mut distance = 0.0;
+        for (start, end) in self.edges() {
+            let edge = end - start;
+            let inward = if area > 0.0 {
+                Vec2::new(-edge.y, edge.x)
+            } else {
+                Vec2::new(edge.y, -edge.x)
+            }
+            .normalize_or_zero();
+            let normal = Vec3::new(inward.x, 0.0, inward.y);
+
+            let corners = [
+                Vec3::new(start.x, 0.0, start.y),
+                Vec3::new(end.x, 0.0, end.y),
+                Vec3::new(end.x, height, end.y),
+                Vec3::new(start.x, height, start.y),
+            ];
+            let length = edge.length();
+            let first = positions.len() as u32;
+            positions.extend(corners.map(|corner| corner.to_array()));
+            normals.extend([normal.to_array(); 4]);
+            uvs.extend([
+                [distance, 0.0],
+                [distance + length, 0.0],
+                [distance + length, height],
+                [distance, height],
+            ]);
+            distance += length;
+
+            let facing = (corners[1] - corners[0])
+                .cross(corners[2] - corners[0])
+                .dot(normal);
+            if facing >= 0.0 {
+                indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+            } else {
+                indices.extend([first, first + 2, first + 1, first, first + 3, first + 2]);
+            }
+        }
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh
+    }
+}
+
+#[derive(Bundle)]
+pub struct XrBoundaryBundle {
+    name: Name,
+    spatial_bundle: SpatialBundle,
+    xr_boundary: XrBoundary,
+    xr_local: XrLocal,
+}
+
+impl XrBoundaryBundle {
+    pub fn default(boundary: XrBoundary) -> Self {
+        Self {
+            name: Name::new("XrBoundary"),
+            spatial_bundle: SpatialBundle::default(),
+            xr_boundary: boundary,
+            xr_local: XrLocal,
+        }
+    }
+}
+
+/// The zone of an entity relative to a [`XrBoundary`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrBoundaryZone {
+    /// Inside and further away from the boundary than the [`XrBoundarySettings::warning_distance`].
+    Inside,
+    /// Inside and closer to the boundary than the [`XrBoundarySettings::warning_distance`].
+    Near,
+    /// Outside of the boundary.
+    Outside,
+}
+
+/// An [`Event`] sent whenever the head, a controller or a hand joint of the local rig moves into another [`XrBoundaryZone`] of a [`XrBoundary`].
+///
+/// No event is sent for entities which are initially [`XrBoundaryZone::Inside`].
+#[derive(Event, Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrBoundaryProximity {
+    pub boundary: Entity,
+    /// The head, controller or hand joint which moved into another zone.
+    pub entity: Entity,
+    pub previous: XrBoundaryZone,
+    pub current: XrBoundaryZone,
+    /// The signed distance to the boundary, see [`XrBoundary::signed_distance`].
+    pub distance: f32,
+}
+
+/// Sends the [`XrBoundaryProximity`] events for the active heads, controllers and [`XrBoundarySettings::hand_joints`] of the local rig.
+#[allow(clippy::type_complexity)]
+pub fn send_boundary_proximity(
+    settings: Res<XrBoundarySettings>,
+    boundaries: Query<(Entity, &XrBoundary, &GlobalTransform)>,
+    tracked: Query<
+        (Entity, &GlobalTransform, &XrActive, Option<&Hand>),
+        (
+            With<XrLocal>,
+            Or<(
+                With<XrHeadset>,
+                With<XrHead>,
+                With<XrController>,
+                With<Hand>,
+            )>,
+        ),
+    >,
+    mut zones: Local<HashMap<(Entity, Entity), XrBoundaryZone>>,
+    mut proximity_events: EventWriter<XrBoundaryProximity>,
+) {
+    let mut current_zones = HashMap::default();
+    for (boundary_entity, boundary, boundary_transform) in boundaries.iter() {
+        for (entity, transform, active, hand) in tracked.iter() {
+            if hand.is_some_and(|hand| !settings.hand_joints.contains(hand)) {
+                continue;
+            }
+            if !active.0 {
+                if let Some(zone) = zones.get(&(boundary_entity, entity)) {
+                    current_zones.insert((boundary_entity, entity), *zone);
+                }
+                continue;
+            }
+            let point = XrBoundary::project(boundary_transform, transform.translation());
+            let distance = boundary.signed_distance(point);
+            let current = if distance < 0.0 {
+                XrBoundaryZone::Outside
+            } else if distance < settings.warning_distance {
+                XrBoundaryZone::Near
+            } else {
+                XrBoundaryZone::Inside
+            };
+
+            let previous = zones
+                .get(&(boundary_entity, entity))
+                .copied()
+                .unwrap_or(XrBoundaryZone::Inside);
+            if previous != current {
+                proximity_events.send(XrBoundaryProximity {
+                    boundary: boundary_entity,
+                    entity,
+                    previous,
+                    current,
+                    distance,
+                });
+            }
+            current_zones.insert((boundary_entity, entity), current);
+        }
+    }
+    *zones = current_zones;
+}
+
+/// Draws the [`XrBoundary`] polygons on the floor.
+pub fn draw_boundary_gizmos(
+    boundaries: Query<(&XrBoundary, &GlobalTransform)>,
+    mut gizmos: Gizmos,
+) {
+    for (boundary, transform) in boundaries.iter() {
+        gizmos.linestrip(
+            boundary
+                .points
+                .iter()
+                .chain(boundary.points.first())
+                .map(|point| transform.transform_point(Vec3::new(point.x, 0.0, point.y))),
+            Color::CYAN,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_distance_inside() {
+        let boundary = XrBoundary::rectangle(Vec2::new(4.0, 2.0));
+        assert_eq!(boundary.signed_distance(Vec2::ZERO), 1.0);
+        assert_eq!(boundary.signed_distance(Vec2::new(1.5, 0.0)), 0.5);
+    }
+
+    #[test]
+    fn signed_distance_outside() {
+        let boundary = XrBoundary::rectangle(Vec2::new(4.0, 2.0));
+        assert_eq!(boundary.signed_distance(Vec2::new(3.0, 0.0)), -1.0);
+        assert!((boundary.signed_distance(Vec2::new(3.0, 2.0)) + 2f32.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn signed_distance_concave() {
+        // An L-shape with the notch at the top right.
+        let boundary = XrBoundary::new(vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(4.0, 0.0),
+            Vec2::new(4.0, 2.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(2.0, 4.0),
+            Vec2::new(0.0, 4.0),
+        ]);
+        assert_eq!(boundary.signed_distance(Vec2::new(1.0, 3.0)), 1.0);
+        assert_eq!(boundary.signed_distance(Vec2::new(3.0, 3.0)), -1.0);
+    }
+
+    #[test]
+    fn signed_distance_without_points() {
+        let boundary = XrBoundary::new(Vec::new());
+        assert_eq!(boundary.signed_distance(Vec2::ZERO), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn proximity_of_hand_joints() {
+        use crate::hands::{
+            finger::Index, finger_joint::Tip, hand_joint::Palm, FingerJointBundle, HandJointBundle,
+            RightHanded,
+        };
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, TransformPlugin, XrBoundaryPlugin));
+        let boundary = app
+            .world
+            .spawn((
+                XrBoundary::rectangle(Vec2::new(4.0, 2.0)),
+                SpatialBundle::default(),
+            ))
+            .id();
+        let tip = app
+            .world
+            .spawn(FingerJointBundle::<RightHanded, Index, Tip>::default())
+            .insert(Transform::from_xyz(3.0, 1.0, 0.0))
+            .id();
+        app.world
+            .spawn(HandJointBundle::<RightHanded, Palm>::default())
+            .insert(Transform::from_xyz(3.0, 1.0, 0.0));
+        app.update();
+
+        let events = app.world.resource::<Events<XrBoundaryProximity>>();
+        let events: Vec<_> = events.get_reader().read(events).copied().collect();
+        assert_eq!(
+            events,
+            vec![XrBoundaryProximity {
+                boundary,
+                entity: tip,
+                previous: XrBoundaryZone::Inside,
+                current: XrBoundaryZone::Outside,
+                distance: -1.0,
+            }]
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Camera settings shared by all [`XrView`]s.
+//!
+//! The views are spawned by the xr platform specific crate, so apps can not configure them through their bundles.
+//! Instead the [`XrCameraSettings`] resource holds the tonemapping, bloom, clear color, render layers and post processing of all views.
+//! Inserted as a component on a [`XrOrigin`] it overrides the resource for the views of this origin.
+//!
+//! The [`XrCameraSettingsPlugin`] applies the settings whenever they change and to every view spawned later.
+//! Optional settings which are [`None`] leave the components of the views untouched, except for removing the ones inserted by earlier settings.
+
+use bevy::{
+    core_pipeline::{
+        bloom::BloomSettings,
+        clear_color::ClearColorConfig,
+        core_3d::Camera3d,
+        tonemapping::{DebandDither, Tonemapping},
+    },
+    ecs::system::EntityCommands,
+    prelude::*,
+    render::{
+        camera::CameraUpdateSystem,
+        view::{ColorGrading, RenderLayers},
+    },
+    utils::HashMap,
+};
+
+use crate::{render::FlipView, space::XrOrigin, XrView};
+
+/// Applies the [`XrCameraSettings`] to all [`XrView`]s.
+pub struct XrCameraSettingsPlugin;
+
+impl Plugin for XrCameraSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrCameraSettings>()
+            .add_systems(PostUpdate, apply_camera_settings.before(CameraUpdateSystem));
+
+        app.register_type::<XrCameraSettings>();
+    }
+}
+
+/// The settings of the cameras of all [`XrView`]s, e.g. the [`crate::head::XrEye`]s and the [`crate::window::XrWindow`]s.
+///
+/// As a resource the settings apply to all views, as a component on a [`XrOrigin`] they apply to the views of this origin only.
+/// The defaults match the defaults of a [`Camera3dBundle`].
+#[derive(Resource, Component, Clone, Reflect)]
+#[reflect(Resource, Component, Default)]
+pub struct XrCameraSettings {
+    /// Renders in high dynamic range, required for bloom.
+    pub hdr: bool,
+    pub clear_color: ClearColorConfig,
+    pub tonemapping: Tonemapping,
+    pub dither: DebandDither,
+    pub color_grading: ColorGrading,
+    /// The bloom of the views, [`None`] keeps the bloom of the views.
+    pub bloom: Option<BloomSettings>,
+    /// The render layers of the views, [`None`] keeps the render layers of the views.
+    pub render_layers: Option<RenderLayers>,
+    /// Flips the rendered image, see [`crate::render::FlipViewPlugin`]. [`None`] keeps the flip of the views.
+    pub flip_view: Option<FlipView>,
+    /// The multisampling of all cameras of the app, [`None`] keeps the [`Msaa`] resource.
+    ///
+    /// [`Msaa`] is a global resource of bevy, so it is only applied from the [`XrCameraSettings`] resource and ignored on a [`XrOrigin`].
+    pub msaa: Option<Msaa>,
+}
+
+impl Default for XrCameraSettings {
+    fn default() -> Self {
+        Self {
+            hdr: false,
+            clear_color: ClearColorConfig::default(),
+            tonemapping: Tonemapping::default(),
+            dither: DebandDither::Enabled,
+            color_grading: ColorGrading::default(),
+            bloom: None,
+            render_layers: None,
+            flip_view: None,
+            msaa: None,
+        }
+    }
+}
+
+/// The optional components inserted on a view by the [`XrCameraSettings`].
+#[derive(Debug, Default, Copy, Clone)]
+pub struct XrInsertedCameraSettings {
+    bloom: bool,
+    render_layers: bool,
+    flip_view: bool,
+}
+
+/// Inserts the component of an optional setting, or removes it if it was `inserted` by earlier settings.
+fn apply_optional_setting<C: Component + Clone>(
+    view: &mut EntityCommands,
+    setting: Option<&C>,
+    inserted: &mut bool,
+) {
+    match setting {
+        Some(component) => {
+            view.insert(component.clone());
+            *inserted = true;
+        }
+        None if *inserted => {
+            view.remove::<C>();
+            *inserted = false;
+        }
+        None => {}
+    }
+}
+
+/// Applies the [`XrCameraSettings`] to all [`XrView`]s when the settings change and to newly spawned views.
+pub fn apply_camera_settings(
+    mut commands: Commands,
+    settings: Res<XrCameraSettings>,
+    origin_settings: Query<Ref<XrCameraSettings>, With<XrOrigin>>,
+    mut removed_origin_settings: RemovedComponents<XrCameraSettings>,
+    mut views: Query<(Entity, Ref<XrView>, &mut Camera, Option<&mut Camera3d>)>,
+    parents: Query<&Parent>,
+    mut inserted_settings: Local<HashMap<Entity, XrInsertedCameraSettings>>,
+) {
+    // Forget the views which were despawned.
+    inserted_settings.retain(|entity, _| views.contains(*entity));
+
+    if settings.is_changed() {
+        if let Some(msaa) = settings.msaa {
+            commands.insert_resource(msaa);
+        }
+    }
+
+    let changed = settings.is_changed()
+        || origin_settings.iter().any(|settings| settings.is_changed())
+        || removed_origin_settings.read().count() > 0;
+
+    for (entity, view, mut camera, camera_3d) in views.iter_mut() {
+        if !changed && !view.is_added() {
+            continue;
+        }
+
+        // The settings of the nearest origin above the view or the resource.
+        let settings = parents
+            .iter_ancestors(entity)
+            .find_map(|ancestor| origin_settings.get(ancestor).ok())
+            .map_or(settings.as_ref(), |settings| settings.into_inner());
+
+        if camera.hdr != settings.hdr {
+            camera.hdr = settings.hdr;
+        }
+        if let Some(mut camera_3d) = camera_3d {
+            camera_3d.clear_color = settings.clear_color.clone();
+        }
+
+        let mut view = commands.entity(entity);
+        view.insert((
+            settings.tonemapping,
+            settings.dither,
+            settings.color_grading,
+        ));
+        let inserted = inserted_settings.entry(entity).or_default();
+        apply_optional_setting(&mut view, settings.bloom.as_ref(), &mut inserted.bloom);
+        apply_optional_setting(
+            &mut view,
+            settings.render_layers.as_ref(),
+            &mut inserted.render_layers,
+        );
+        apply_optional_setting(
+            &mut view,
+            settings.flip_view.as_ref(),
+            &mut inserted.flip_view,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, HierarchyPlugin, XrCameraSettingsPlugin));
+        app
+    }
+
+    fn spawn_view(app: &mut App, index: u8) -> Entity {
+        app.world
+            .spawn((XrView(index), Camera::default(), Camera3d::default()))
+            .id()
+    }
+
+    #[test]
+    fn view_spawned_later() {
+        let mut app = app();
+        {
+            let mut settings = app.world.resource_mut::<XrCameraSettings>();
+            settings.hdr = true;
+            settings.tonemapping = Tonemapping::AcesFitted;
+        }
+        app.update();
+        app.update();
+
+        let view = spawn_view(&mut app, 0);
+        app.update();
+        assert!(app.world.get::<Camera>(view).unwrap().hdr);
+        assert_eq!(
+            *app.world.get::<Tonemapping>(view).unwrap(),
+            Tonemapping::AcesFitted
+        );
+    }
+
+    #[test]
+    fn origin_overrides_resource() {
+        let mut app = app();
+        app.world.resource_mut::<XrCameraSettings>().render_layers = Some(RenderLayers::layer(1));
+        let origin = app
+            .world
+            .spawn((
+                XrOrigin::Room,
+                XrCameraSettings {
+                    hdr: true,
+                    render_layers: Some(RenderLayers::layer(2)),
+                    ..default()
+                },
+            ))
+            .id();
+        let origin_view = spawn_view(&mut app, 0);
+        app.world.entity_mut(origin_view).set_parent(origin);
+        let other_view = spawn_view(&mut app, 1);
+        app.update();
+
+        assert!(app.world.get::<Camera>(origin_view).unwrap().hdr);
+        assert_eq!(
+            app.world.get::<RenderLayers>(origin_view),
+            Some(&RenderLayers::layer(2))
+        );
+        assert!(!app.world.get::<Camera>(other_view).unwrap().hdr);
+        assert_eq!(
+            app.world.get::<RenderLayers>(other_view),
+            Some(&RenderLayers::layer(1))
+        );
+
+        // Removing the override falls back to the resource.
+        app.world.entity_mut(origin).remove::<XrCameraSettings>();
+        app.update();
+        assert!(!app.world.get::<Camera>(origin_view).unwrap().hdr);
+        assert_eq!(
+            app.world.get::<RenderLayers>(origin_view),
+            Some(&RenderLayers::layer(1))
+        );
+    }
+
+    #[test]
+    fn unset_removes_only_inserted_components() {
+        let mut app = app();
+        let view = spawn_view(&mut app, 0);
+        app.world
+            .entity_mut(view)
+            .insert((RenderLayers::layer(5), FlipView::Y));
+        {
+            let mut settings = app.world.resource_mut::<XrCameraSettings>();
+            settings.bloom = Some(BloomSettings::default());
+            settings.flip_view = Some(FlipView::X);
+        }
+        app.update();
+        assert!(app.world.get::<BloomSettings>(view).is_some());
+        assert_eq!(app.world.get::<FlipView>(view), Some(&FlipView::X));
+        assert_eq!(
+            app.world.get::<RenderLayers>(view),
+            Some(&RenderLayers::layer(5))
+        );
+
+        {
+            let mut settings = app.world.resource_mut::<XrCameraSettings>();
+            settings.bloom = None;
+            settings.flip_view = None;
+        }
+        app.update();
+        assert!(app.world.get::<BloomSettings>(view).is_none());
+        assert!(app.world.get::<FlipView>(view).is_none());
+        // The render layers inserted by the app are kept.
+        assert_eq!(
+            app.world.get::<RenderLayers>(view),
+            Some(&RenderLayers::layer(5))
+        );
+    }
+}
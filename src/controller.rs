@@ -4,6 +4,7 @@ pub use crate::space::XrOrigin;
 use crate::IntoEnum;
 pub use crate::XrActive;
 pub use crate::XrLocal;
+pub use crate::XrTrackingState;
 
 pub use crate::handedness::*;
 
@@ -18,19 +19,32 @@ pub use crate::tracked::XrTrackedObject;
 ///
 /// This component should be spawned including a  [`SpatialBundle`] or similar.
 #[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
-#[reflect(Debug, Hash, PartialEq)]
+#[reflect(Component, Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub enum XrController {
     Right,
     Left,
     Other(u8),
 }
 
+/// Reflection inserts the right controller and then applies the reflected one.
+impl FromWorld for XrController {
+    fn from_world(_world: &mut World) -> Self {
+        Self::Right
+    }
+}
+
 #[derive(Bundle)]
 pub struct XrControllerBundle<Handed: HandednessMarker> {
     name: Name,
     spatial_bundle: SpatialBundle,
     xr_local: XrLocal,
     xr_active: XrActive,
+    xr_tracking_state: XrTrackingState,
     handedness: Handed,
     handedness_enum: Handedness,
     xr_controller: XrController,
@@ -46,6 +60,7 @@ impl<Handed: HandednessMarker + IntoEnum<XrController>> XrControllerBundle<Hande
             spatial_bundle: SpatialBundle::default(),
             xr_local: XrLocal,
             xr_active: XrActive(true),
+            xr_tracking_state: XrTrackingState::Tracked,
             handedness,
             handedness_enum: Handed::into_enum(),
             xr_controller: Handed::into_enum(),
@@ -60,6 +75,7 @@ pub struct XrControllerHandlessBundle {
     spatial_bundle: SpatialBundle,
     xr_local: XrLocal,
     xr_active: XrActive,
+    xr_tracking_state: XrTrackingState,
     xr_controller: XrController,
     xr_tracked_object: XrTrackedObject,
 }
@@ -71,6 +87,7 @@ impl XrControllerHandlessBundle {
             spatial_bundle: SpatialBundle::default(),
             xr_local: XrLocal,
             xr_active: XrActive(true),
+            xr_tracking_state: XrTrackingState::Tracked,
             xr_controller: XrController::Other(index),
             xr_tracked_object: XrTrackedObject(index),
         }
@@ -88,6 +88,11 @@ impl Plugin for XrControllerInputPlugin {
 /// [`Axis<XrControllerTouch>`] and [`Axis<XrControllerPress>`] `bevy` resources.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
 #[reflect(Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub enum XrControllerInputType {
     AorX, // this is better in case of web xr, feedback needed.
     BorY,
@@ -0,0 +1,333 @@
+//! Eye tracking, so apps can select what the user looks at and analyze where the user looked.
+//!
+//! The xr platform specific crate submits the [`XrEyeGaze`] of every frame with [`crate::backend::XrBackendFrame::submit_eye_gaze`] or inserts it on the local [`XrOrigin`] itself.
+//! The gaze holds a [`XrGazeRay`] per eye, the combined ray of both eyes and the openness of the eyes.
+//!
+//! The [`XrGazePlugin`] spawns a [`XrPointer::Eye`] entity as a child of every [`XrOrigin`] with a [`XrEyeGaze`].
+//! The pointer follows the combined gaze and loses its tracking while the confidence is low or the eyes are closed,
+//! its [`XrGazeFixation`] holds the point both eyes converge on.
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    handedness::Handedness, pointer::XrPointer, space::XrOrigin, XrActive, XrLocal, XrSet,
+    XrTrackingState,
+};
+
+pub struct XrGazePlugin;
+
+impl Plugin for XrGazePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrGazeSettings>().add_systems(
+            PreUpdate,
+            update_gaze_pointers
+                .after(XrSet::BackendSync)
+                .before(XrSet::Derive),
+        );
+
+        app.register_type::<XrGazeRay>()
+            .register_type::<XrEyeGaze>()
+            .register_type::<XrGazeFixation>()
+            .register_type::<XrGazeSettings>();
+    }
+}
+
+/// Settings of the [`XrGazePlugin`].
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Debug, Default)]
+pub struct XrGazeSettings {
+    /// The minimum confidence of the combined gaze for the [`XrPointer::Eye`] to be tracked.
+    pub min_confidence: f32,
+    /// The openness below which an eye counts as closed, see [`XrEyeGaze::is_blinking`].
+    pub blink_threshold: f32,
+    /// The maximum distance of the [`XrGazeFixation`] in meters, the vergence of nearly parallel rays is too imprecise beyond.
+    pub max_fixation_distance: f32,
+}
+
+impl Default for XrGazeSettings {
+    fn default() -> Self {
+        Self {
+            min_confidence: 0.5,
+            blink_threshold: 0.2,
+            max_fixation_distance: 10.0,
+        }
+    }
+}
+
+/// A gaze ray relative to the [`XrOrigin`].
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrGazeRay {
+    pub origin: Vec3,
+    /// The normalized direction of the gaze.
+    pub direction: Vec3,
+    /// The confidence of the runtime in the ray from `0` to `1`, `0` if the eye is not tracked.
+    pub confidence: f32,
+}
+
+impl Default for XrGazeRay {
+    fn default() -> Self {
+        Self {
+            origin: Vec3::ZERO,
+            direction: Vec3::NEG_Z,
+            confidence: 0.0,
+        }
+    }
+}
+
+impl XrGazeRay {
+    pub fn new(origin: Vec3, direction: Vec3, confidence: f32) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize_or_zero(),
+            confidence,
+        }
+    }
+
+    pub fn ray(&self) -> Ray {
+        Ray {
+            origin: self.origin,
+            direction: self.direction,
+        }
+    }
+
+    /// The transform at the origin of the ray looking along the ray.
+    pub fn transform(&self) -> Transform {
+        Transform::from_translation(self.origin).looking_to(self.direction, Vec3::Y)
+    }
+}
+
+/// The eye gaze of the user reported by the runtime.
+///
+/// This component is inserted on the local [`XrOrigin`], all rays are relative to it.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrEyeGaze {
+    pub left: XrGazeRay,
+    pub right: XrGazeRay,
+    /// The gaze of both eyes combined by the runtime, usually originating between the eyes.
+    pub combined: XrGazeRay,
+    /// The openness of the left eye from `0` closed to `1` open.
+    pub left_openness: f32,
+    /// The openness of the right eye from `0` closed to `1` open.
+    pub right_openness: f32,
+}
+
+impl Default for XrEyeGaze {
+    fn default() -> Self {
+        Self {
+            left: XrGazeRay::default(),
+            right: XrGazeRay::default(),
+            combined: XrGazeRay::default(),
+            left_openness: 1.0,
+            right_openness: 1.0,
+        }
+    }
+}
+
+impl XrEyeGaze {
+    /// Creates a gaze of both eyes, combined by the average of the rays and the lower confidence.
+    pub fn from_eyes(left: XrGazeRay, right: XrGazeRay) -> Self {
+        Self {
+            left,
+            right,
+            combined: XrGazeRay::new(
+                (left.origin + right.origin) / 2.0,
+                left.direction + right.direction,
+                left.confidence.min(right.confidence),
+            ),
+            ..default()
+        }
+    }
+
+    pub fn eye(&self, handedness: Handedness) -> XrGazeRay {
+        match handedness {
+            Handedness::Left => self.left,
+            Handedness::Right => self.right,
+        }
+    }
+
+    pub fn openness(&self, handedness: Handedness) -> f32 {
+        match handedness {
+            Handedness::Left => self.left_openness,
+            Handedness::Right => self.right_openness,
+        }
+    }
+
+    /// Are both eyes closed below the threshold?
+    pub fn is_blinking(&self, threshold: f32) -> bool {
+        self.left_openness < threshold && self.right_openness < threshold
+    }
+
+    /// The point the rays of both eyes converge on, the midpoint of the closest points of the two rays.
+    ///
+    /// Returns [`None`] for parallel or diverging rays and for untracked eyes.
+    pub fn vergence_point(&self) -> Option<Vec3> {
+        if self.left.confidence <= 0.0 || self.right.confidence <= 0.0 {
+            return None;
+        }
+        let offset = self.left.origin - self.right.origin;
+        let a = self.left.direction.length_squared();
+        let b = self.left.direction.dot(self.right.direction);
+        let c = self.right.direction.length_squared();
+        let d = self.left.direction.dot(offset);
+        let e = self.right.direction.dot(offset);
+        let denominator = a * c - b * b;
+        if denominator <= f32::EPSILON {
+            return None;
+        }
+        let left = (b * e - c * d) / denominator;
+        let right = (a * e - b * d) / denominator;
+        if left <= 0.0 || right <= 0.0 {
+            return None;
+        }
+        Some(
+            (self.left.origin
+                + left * self.left.direction
+                + self.right.origin
+                + right * self.right.direction)
+                / 2.0,
+        )
+    }
+}
+
+/// The point the eyes converge on, relative to the [`XrOrigin`], see [`XrEyeGaze::vergence_point`].
+///
+/// This component is updated on the [`XrPointer::Eye`] entity spawned by the [`XrGazePlugin`], [`None`] while the vergence can not be determined.
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrGazeFixation(pub Option<Vec3>);
+
+#[derive(Bundle)]
+pub struct XrGazePointerBundle {
+    name: Name,
+    spatial_bundle: SpatialBundle,
+    xr_local: XrLocal,
+    xr_active: XrActive,
+    xr_tracking_state: XrTrackingState,
+    xr_pointer: XrPointer,
+    xr_gaze_fixation: XrGazeFixation,
+}
+
+impl XrGazePointerBundle {
+    pub fn default(transform: Transform, tracking_state: XrTrackingState) -> Self {
+        Self {
+            name: Name::new("XrPointer_Eye"),
+            spatial_bundle: SpatialBundle::from_transform(transform),
+            xr_local: XrLocal,
+            xr_active: tracking_state.into(),
+            xr_tracking_state: tracking_state,
+            xr_pointer: XrPointer::Eye,
+            xr_gaze_fixation: XrGazeFixation::default(),
+        }
+    }
+}
+
+/// Spawns a [`XrPointer::Eye`] for every [`XrOrigin`] with a [`XrEyeGaze`] and moves it along the combined gaze.
+pub fn update_gaze_pointers(
+    mut commands: Commands,
+    settings: Res<XrGazeSettings>,
+    origins: Query<(Entity, Ref<XrEyeGaze>), With<XrOrigin>>,
+    mut pointers: Query<(&mut Transform, &mut XrTrackingState, &mut XrGazeFixation)>,
+    mut entities: Local<HashMap<Entity, Entity>>,
+) {
+    // Forget the pointers which were despawned by the app.
+    entities.retain(|_, pointer| pointers.contains(*pointer));
+
+    for (origin, gaze) in origins.iter() {
+        let tracked = gaze.combined.confidence >= settings.min_confidence
+            && !gaze.is_blinking(settings.blink_threshold);
+        let tracking_state = match tracked {
+            true => XrTrackingState::Tracked,
+            false => XrTrackingState::Lost,
+        };
+        let fixation = XrGazeFixation(gaze.vergence_point().filter(|point| {
+            tracked && point.distance(gaze.combined.origin) <= settings.max_fixation_distance
+        }));
+
+        let Some(pointer) = entities.get(&origin) else {
+            let pointer = commands
+                .spawn(XrGazePointerBundle::default(
+                    gaze.combined.transform(),
+                    tracking_state,
+                ))
+                .insert(fixation)
+                .id();
+            commands.entity(origin).add_child(pointer);
+            entities.insert(origin, pointer);
+            continue;
+        };
+        if !gaze.is_changed() {
+            continue;
+        }
+        let Ok((mut transform, mut current_state, mut current_fixation)) =
+            pointers.get_mut(*pointer)
+        else {
+            continue;
+        };
+        // A lost pointer keeps the last tracked pose.
+        if tracked {
+            transform.set_if_neq(gaze.combined.transform());
+        }
+        current_state.set_if_neq(tracking_state);
+        current_fixation.set_if_neq(fixation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A gaze of eyes 6 cm apart looking at the `left` and `right` directions.
+    fn gaze(left: Vec3, right: Vec3) -> XrEyeGaze {
+        XrEyeGaze::from_eyes(
+            XrGazeRay::new(Vec3::new(-0.03, 0.0, 0.0), left, 1.0),
+            XrGazeRay::new(Vec3::new(0.03, 0.0, 0.0), right, 1.0),
+        )
+    }
+
+    #[test]
+    fn vergence_point_converging() {
+        let target = Vec3::new(0.0, 0.0, -0.5);
+        let gaze = gaze(
+            target - Vec3::new(-0.03, 0.0, 0.0),
+            target - Vec3::new(0.03, 0.0, 0.0),
+        );
+        let point = gaze.vergence_point().unwrap();
+        assert!(point.distance(target) < 1e-5);
+    }
+
+    #[test]
+    fn vergence_point_parallel() {
+        assert_eq!(gaze(Vec3::NEG_Z, Vec3::NEG_Z).vergence_point(), None);
+    }
+
+    #[test]
+    fn vergence_point_diverging() {
+        let gaze = gaze(Vec3::new(-0.1, 0.0, -1.0), Vec3::new(0.1, 0.0, -1.0));
+        assert_eq!(gaze.vergence_point(), None);
+    }
+
+    #[test]
+    fn vergence_point_untracked() {
+        let mut gaze = gaze(Vec3::new(0.03, 0.0, -1.0), Vec3::new(-0.03, 0.0, -1.0));
+        assert!(gaze.vergence_point().is_some());
+        gaze.right.confidence = 0.0;
+        assert_eq!(gaze.vergence_point(), None);
+    }
+}
@@ -10,12 +10,28 @@ use crate::IntoEnum;
 /// This [`Component`] allows for querying entities of both hands while getting the handedness information. For only querying one side [`XrLeft`] and [`XrRight`] components are available.
 ///
 /// This component should be spawned with entities that belong to one side of the body.
-#[derive(Component)]
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub enum Handedness {
     Right,
     Left,
 }
 
+/// Reflection needs a value to apply a reflected [`Handedness`] to when inserting it, e.g. from a scene.
+///
+/// The sides of the body have no neutral value, so this placeholder is not exposed as [`Default`].
+/// The other semantic components of this crate implement [`FromWorld`] the same way.
+impl FromWorld for Handedness {
+    fn from_world(_world: &mut World) -> Self {
+        Self::Right
+    }
+}
+
 pub trait HandednessMarker: Component + Reflect + IntoEnum<Handedness> + Default + Sized
 where
     Self: Sized,
@@ -29,6 +45,12 @@ where
 ///
 /// This component should be spawned with entities that belong to the left side of the body.
 #[derive(Component, Reflect, Default)]
+#[reflect(Component, Default)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub struct LeftHanded;
 
 /// The defining [`Component`] for entities that belong to right side of the body.
@@ -38,6 +60,12 @@ pub struct LeftHanded;
 ///
 /// This component should be spawned with entities that belong to the right side of the body.
 #[derive(Component, Reflect, Default)]
+#[reflect(Component, Default)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub struct RightHanded;
 
 //
@@ -3,10 +3,17 @@ use bevy::prelude::*;
 pub use crate::space::XrOrigin;
 pub use crate::XrActive;
 pub use crate::XrLocal;
+pub use crate::XrTrackingState;
 
 pub use crate::handedness::*;
 
-#[derive(Component, Reflect)]
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub enum Hand {
     Forearm,
     Wrist,
@@ -37,7 +44,20 @@ pub enum Hand {
     LittleTip,
 }
 
-#[derive(Component, Reflect, Default)]
+/// Reflection inserts the forearm and then applies the reflected joint.
+impl FromWorld for Hand {
+    fn from_world(_world: &mut World) -> Self {
+        Self::Forearm
+    }
+}
+
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub struct HandJointRadius(pub Option<f32>);
 
 pub mod hand_joint {
@@ -54,12 +74,30 @@ pub mod hand_joint {
     }
 
     #[derive(Component, Reflect, Default)]
+    #[reflect(Component, Default)]
+    #[cfg_attr(
+        feature = "serialize",
+        derive(serde::Serialize, serde::Deserialize),
+        reflect(Serialize, Deserialize)
+    )]
     pub struct Forearm;
 
     #[derive(Component, Reflect, Default)]
+    #[reflect(Component, Default)]
+    #[cfg_attr(
+        feature = "serialize",
+        derive(serde::Serialize, serde::Deserialize),
+        reflect(Serialize, Deserialize)
+    )]
     pub struct Wrist;
 
     #[derive(Component, Reflect, Default)]
+    #[reflect(Component, Default)]
+    #[cfg_attr(
+        feature = "serialize",
+        derive(serde::Serialize, serde::Deserialize),
+        reflect(Serialize, Deserialize)
+    )]
     pub struct Palm;
 }
 
@@ -68,7 +106,13 @@ pub mod finger {
 
     use crate::IntoEnum;
 
-    #[derive(Component, Reflect)]
+    #[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+    #[reflect(Component, Debug, Hash, PartialEq)]
+    #[cfg_attr(
+        feature = "serialize",
+        derive(serde::Serialize, serde::Deserialize),
+        reflect(Serialize, Deserialize)
+    )]
     pub enum Finger {
         Thumb,
         Index,
@@ -77,6 +121,13 @@ pub mod finger {
         Little,
     }
 
+    /// Reflection inserts the thumb and then applies the reflected finger.
+    impl FromWorld for Finger {
+        fn from_world(_world: &mut World) -> Self {
+            Self::Thumb
+        }
+    }
+
     pub trait FingerMarker: Component + Reflect + IntoEnum<Finger> + Default + Sized
     where
         Self: Sized,
@@ -84,18 +135,48 @@ pub mod finger {
     }
 
     #[derive(Component, Reflect, Default)]
+    #[reflect(Component, Default)]
+    #[cfg_attr(
+        feature = "serialize",
+        derive(serde::Serialize, serde::Deserialize),
+        reflect(Serialize, Deserialize)
+    )]
     pub struct Thumb;
 
     #[derive(Component, Reflect, Default)]
+    #[reflect(Component, Default)]
+    #[cfg_attr(
+        feature = "serialize",
+        derive(serde::Serialize, serde::Deserialize),
+        reflect(Serialize, Deserialize)
+    )]
     pub struct Index;
 
     #[derive(Component, Reflect, Default)]
+    #[reflect(Component, Default)]
+    #[cfg_attr(
+        feature = "serialize",
+        derive(serde::Serialize, serde::Deserialize),
+        reflect(Serialize, Deserialize)
+    )]
     pub struct Middle;
 
     #[derive(Component, Reflect, Default)]
+    #[reflect(Component, Default)]
+    #[cfg_attr(
+        feature = "serialize",
+        derive(serde::Serialize, serde::Deserialize),
+        reflect(Serialize, Deserialize)
+    )]
     pub struct Ring;
 
     #[derive(Component, Reflect, Default)]
+    #[reflect(Component, Default)]
+    #[cfg_attr(
+        feature = "serialize",
+        derive(serde::Serialize, serde::Deserialize),
+        reflect(Serialize, Deserialize)
+    )]
     pub struct Little;
 }
 
@@ -104,7 +185,13 @@ pub mod finger_joint {
 
     use crate::IntoEnum;
 
-    #[derive(Component, Reflect)]
+    #[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+    #[reflect(Component, Debug, Hash, PartialEq)]
+    #[cfg_attr(
+        feature = "serialize",
+        derive(serde::Serialize, serde::Deserialize),
+        reflect(Serialize, Deserialize)
+    )]
     pub enum FingerJoint {
         Metacarpal,
         ProximalPhalanx,
@@ -113,6 +200,13 @@ pub mod finger_joint {
         Tip,
     }
 
+    /// Reflection inserts the metacarpal and then applies the reflected finger joint.
+    impl FromWorld for FingerJoint {
+        fn from_world(_world: &mut World) -> Self {
+            Self::Metacarpal
+        }
+    }
+
     pub trait FingerJointMarker: Component + Reflect + IntoEnum<FingerJoint> + Default
     where
         Self: Sized,
@@ -120,18 +214,48 @@ pub mod finger_joint {
     }
 
     #[derive(Component, Reflect, Default)]
+    #[reflect(Component, Default)]
+    #[cfg_attr(
+        feature = "serialize",
+        derive(serde::Serialize, serde::Deserialize),
+        reflect(Serialize, Deserialize)
+    )]
     pub struct Metacarpal;
 
     #[derive(Component, Reflect, Default)]
+    #[reflect(Component, Default)]
+    #[cfg_attr(
+        feature = "serialize",
+        derive(serde::Serialize, serde::Deserialize),
+        reflect(Serialize, Deserialize)
+    )]
     pub struct ProximalPhalanx;
 
     #[derive(Component, Reflect, Default)]
+    #[reflect(Component, Default)]
+    #[cfg_attr(
+        feature = "serialize",
+        derive(serde::Serialize, serde::Deserialize),
+        reflect(Serialize, Deserialize)
+    )]
     pub struct IntermediatePhalanx;
 
     #[derive(Component, Reflect, Default)]
+    #[reflect(Component, Default)]
+    #[cfg_attr(
+        feature = "serialize",
+        derive(serde::Serialize, serde::Deserialize),
+        reflect(Serialize, Deserialize)
+    )]
     pub struct DistalPhalanx;
 
     #[derive(Component, Reflect, Default)]
+    #[reflect(Component, Default)]
+    #[cfg_attr(
+        feature = "serialize",
+        derive(serde::Serialize, serde::Deserialize),
+        reflect(Serialize, Deserialize)
+    )]
     pub struct Tip;
 }
 
@@ -145,6 +269,7 @@ pub struct HandJointBundle<Handed: HandednessMarker, HandJoint: HandJointMarker>
     pub spatial_bundle: SpatialBundle,
     pub xr_local: XrLocal,
     pub xr_active: XrActive,
+    pub xr_tracking_state: XrTrackingState,
     pub handedness: Handed,
     pub handedness_enum: Handedness,
     pub hand_joint: HandJoint,
@@ -166,6 +291,7 @@ impl<Handed: HandednessMarker, HandJoint: HandJointMarker> Default
             spatial_bundle: SpatialBundle::default(),
             xr_local: XrLocal,
             xr_active: XrActive(true),
+            xr_tracking_state: XrTrackingState::Tracked,
             handedness,
             handedness_enum: Handed::into_enum(),
             hand_joint,
@@ -187,6 +313,7 @@ pub struct FingerJointBundle<
     pub spatial_bundle: SpatialBundle,
     pub xr_local: XrLocal,
     pub xr_active: XrActive,
+    pub xr_tracking_state: XrTrackingState,
     pub handedness: Handed,
     pub handedness_enum: Handedness,
     pub finger: Finger,
@@ -215,6 +342,7 @@ where
             spatial_bundle: SpatialBundle::default(),
             xr_local: XrLocal,
             xr_active: XrActive(true),
+            xr_tracking_state: XrTrackingState::Tracked,
             handedness,
             handedness_enum: Handed::into_enum(),
             finger,
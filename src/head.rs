@@ -5,6 +5,7 @@ use bevy::prelude::*;
 pub use crate::space::XrOrigin;
 pub use crate::XrActive;
 pub use crate::XrLocal;
+pub use crate::XrTrackingState;
 pub use crate::XrView;
 
 /// The defining [`Component`] for head entities,
@@ -13,7 +14,13 @@ pub use crate::XrView;
 /// Head entities should be parented to a [`XrOrigin`] entity and include a [`XrActive`].
 ///
 /// This component should be spawned including a  [`SpatialBundle`] or similar.
-#[derive(Component)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component, Default)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub struct XrHead;
 
 /// The defining [`Component`] for headset entities,
@@ -22,9 +29,76 @@ pub struct XrHead;
 /// Headset entities should be parented to a [`XrOrigin`] entity and include a [`XrActive`].
 ///
 /// This component should be spawned including a  [`SpatialBundle`] or similar.
-#[derive(Component)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component, Default)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub struct XrHeadset;
 
+/// The neck model of a [`XrHeadset`], used to derive the [`XrHead`] bone from the pose of the headset.
+///
+/// Insert this component on a [`XrHeadset`] entity and a [`XrHead`] sibling is spawned and moved with the headset, as the head bone pivots behind and below the eyes.
+/// The spawned head is despawned when the component is removed.
+/// For rotation only devices the position of the headset is synthesized from its orientation, with the head bone resting at the `head_height`.
+/// The [`XrEye`]s beside the headset keep their pose relative to the synthesized headset, eyes parented to the headset follow it.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrNeckModel {
+    /// The offset of the headset from the head bone in the space of the head in meters.
+    pub headset_offset: Vec3,
+    /// Does the device track the orientation only?
+    pub rotation_only: bool,
+    /// The height of the head bone above the [`XrOrigin`] in meters, only used for rotation only devices.
+    pub head_height: f32,
+}
+
+impl Default for XrNeckModel {
+    fn default() -> Self {
+        Self {
+            headset_offset: Vec3::new(0.0, 0.075, -0.0805),
+            rotation_only: false,
+            head_height: 1.5,
+        }
+    }
+}
+
+impl XrNeckModel {
+    /// A neck model for a rotation only device with the head bone at the height above the [`XrOrigin`].
+    pub fn rotation_only(head_height: f32) -> Self {
+        Self {
+            rotation_only: true,
+            head_height,
+            ..default()
+        }
+    }
+
+    /// The transform of the head bone for the transform of the headset.
+    pub fn head_transform(&self, headset: &Transform) -> Transform {
+        let translation = match self.rotation_only {
+            true => Vec3::Y * self.head_height,
+            false => headset.translation - headset.rotation * self.headset_offset,
+        };
+        Transform::from_translation(translation).with_rotation(headset.rotation)
+    }
+
+    /// The transform of the headset with the position synthesized from its orientation, see [`XrNeckModel::rotation_only`].
+    pub fn headset_transform(&self, headset: &Transform) -> Transform {
+        let head = self.head_transform(headset);
+        Transform {
+            translation: head.translation + head.rotation * self.headset_offset,
+            ..*headset
+        }
+    }
+}
+
 #[derive(Bundle)]
 pub struct XrHeadsetBundle {
     name: Name,
@@ -32,16 +106,18 @@ pub struct XrHeadsetBundle {
     xr_headset: XrHeadset,
     xr_local: XrLocal,
     xr_active: XrActive,
+    xr_tracking_state: XrTrackingState,
 }
 
 impl XrHeadsetBundle {
     pub fn default() -> Self {
         Self {
-            name: Name::new("XrOrigin"),
+            name: Name::new("XrHeadset"),
             spatial_bundle: SpatialBundle::default(),
             xr_headset: XrHeadset,
             xr_local: XrLocal,
             xr_active: XrActive(true),
+            xr_tracking_state: XrTrackingState::Tracked,
         }
     }
 }
@@ -51,32 +127,42 @@ pub use crate::handedness::LeftHanded;
 pub use crate::handedness::RightHanded;
 
 use crate::handedness::HandednessMarker;
+use crate::projection::{XrFovCameraBundle, XrViewConfig};
 
 /// The defining [`Component`] for eye entities,
 /// Represents the transform an eye.
 ///
 /// Eye entities should be indexed per handedness. Meaning there should be two [`XrEye`] entities per index.
+/// The [`XrView`] of an eye is allocated by the caller, e.g. with [`XrView::eye`].
 ///
 /// Head entities should be parented to a [`XrOrigin`] entity and include a [`XrActive`]. TODO: Check if this is always the case or if eyes are sometimes parented to the head.
 ///
-/// This component should be spawned including a  [`Camera3dBundle`] or similar and should be marked by a [`XrHandedness`] and one of the [`XrLeft`] or [`XrRight`] components.
-#[derive(Component)]
+/// This component should be spawned including a [`XrFovCameraBundle`] or similar and a [`XrViewConfig`] and should be marked by a [`XrHandedness`] and one of the [`XrLeft`] or [`XrRight`] components.
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub struct XrEye(pub u8);
 
 #[derive(Bundle)]
 pub struct XrEyeBundle<Handed: HandednessMarker> {
     name: Name,
-    camera_bundle: Camera3dBundle,
+    camera_bundle: XrFovCameraBundle,
     xr_local: XrLocal,
     xr_active: XrActive,
+    xr_tracking_state: XrTrackingState,
     xr_view: XrView,
+    xr_view_config: XrViewConfig,
     handedness: Handed,
     handedness_enum: Handedness,
     xr_eye: XrEye,
 }
 
 impl<Handed: HandednessMarker> XrEyeBundle<Handed> {
-    pub fn default(index: u8) -> Self {
+    pub fn default(index: u8, view: XrView) -> Self {
         let handedness = Handed::default();
         let name = "XrEye_".to_string()
             + handedness.reflect_type_ident().unwrap()
@@ -84,10 +170,12 @@ impl<Handed: HandednessMarker> XrEyeBundle<Handed> {
             + &index.to_string();
         Self {
             name: Name::new(name),
-            camera_bundle: Camera3dBundle::default(),
+            camera_bundle: XrFovCameraBundle::default(),
             xr_local: XrLocal,
             xr_active: XrActive(true),
-            xr_view: XrView(index),
+            xr_tracking_state: XrTrackingState::Tracked,
+            xr_view: view,
+            xr_view_config: XrViewConfig::default(),
             handedness,
             handedness_enum: Handed::into_enum(),
             xr_eye: XrEye(index),
@@ -0,0 +1,388 @@
+//! Tracking of printed images and fiducial markers, so content can be anchored to posters or QR codes.
+//!
+//! The images to track are collected in a [`XrReferenceImageLibrary`] asset, the [`XrImageTrackingSettings`] tell the xr platform specific crate which library and which [`XrMarkerKind`]s to track.
+//! The xr platform specific crate sends a [`XrLocateImage`] event for every detected instance of an image or a marker.
+//! The [`XrImagePlugin`] spawns an entity with a [`XrTrackedImage`] or a [`XrTrackedMarker`] for every instance as a child of the local [`XrOrigin`],
+//! updates its pose and [`XrTrackingState`] and sends a [`XrImageEvent`] when the instance is detected or lost.
+//!
+//! The [`XrImagePlugin`] requires the [`AssetPlugin`].
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{space::XrOrigin, XrActive, XrLocal, XrSet, XrTrackingState};
+
+pub struct XrImagePlugin;
+
+impl Plugin for XrImagePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<XrReferenceImageLibrary>()
+            .init_resource::<XrImageTrackingSettings>()
+            .add_event::<XrLocateImage>()
+            .add_event::<XrImageEvent>()
+            .add_systems(
+                PreUpdate,
+                apply_image_events
+                    .after(XrSet::BackendSync)
+                    .before(XrSet::Derive),
+            );
+
+        app.register_type::<XrTrackedImage>()
+            .register_type::<XrTrackedMarker>()
+            .register_type::<XrMarkerKind>()
+            .register_type::<XrImageTarget>()
+            .register_type::<XrLocateImage>()
+            .register_type::<XrImageEvent>();
+    }
+}
+
+/// An image which can be tracked, e.g. a printed poster.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XrReferenceImage {
+    pub name: String,
+    pub image: Handle<Image>,
+    /// The physical width and height of the printed image in meters.
+    pub physical_size: Vec2,
+}
+
+/// An [`Asset`] with the images the runtime should track, the index of an image is used by the [`XrTrackedImage`].
+#[derive(Asset, TypePath, Debug, Default, Clone, PartialEq)]
+pub struct XrReferenceImageLibrary {
+    pub images: Vec<XrReferenceImage>,
+}
+
+impl XrReferenceImageLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an image with its physical size in meters.
+    pub fn with_image(
+        mut self,
+        name: impl Into<String>,
+        image: Handle<Image>,
+        physical_size: Vec2,
+    ) -> Self {
+        self.images.push(XrReferenceImage {
+            name: name.into(),
+            image,
+            physical_size,
+        });
+        self
+    }
+
+    /// Returns the index of the image with the name.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.images.iter().position(|image| image.name == name)
+    }
+}
+
+/// The kind of a fiducial marker.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrMarkerKind {
+    QrCode,
+    ArUco,
+    AprilTag,
+}
+
+/// Settings read by the xr platform specific crate to configure the image tracking of the runtime.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct XrImageTrackingSettings {
+    /// The library of the images to track, [`None`] disables the image tracking.
+    pub library: Option<Handle<XrReferenceImageLibrary>>,
+    /// The kinds of markers to track.
+    pub markers: Vec<XrMarkerKind>,
+}
+
+/// The defining [`Component`] for a detected instance of an image of the [`XrReferenceImageLibrary`].
+///
+/// The image lies in the local `x` `z` plane of the entity, facing the local `y` axis.
+#[derive(Component, Debug, Default, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrTrackedImage {
+    /// The index of the image in the [`XrReferenceImageLibrary`].
+    pub index: usize,
+    pub name: String,
+    /// The physical size of the detected image in meters, which may differ from the size in the library.
+    pub size: Vec2,
+}
+
+/// The defining [`Component`] for a detected fiducial marker.
+///
+/// The marker lies in the local `x` `z` plane of the entity, facing the local `y` axis.
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrTrackedMarker {
+    pub kind: XrMarkerKind,
+    /// The id of an ArUco or AprilTag marker.
+    pub id: u32,
+    /// The decoded data of a QR code.
+    pub data: String,
+    /// The physical size of the detected marker in meters.
+    pub size: Vec2,
+}
+
+/// Reflection inserts an empty QR code and then applies the reflected marker.
+impl FromWorld for XrTrackedMarker {
+    fn from_world(_world: &mut World) -> Self {
+        Self {
+            kind: XrMarkerKind::QrCode,
+            id: 0,
+            data: String::new(),
+            size: Vec2::ZERO,
+        }
+    }
+}
+
+/// What a [`XrLocateImage`] event has detected.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrImageTarget {
+    Image(XrTrackedImage),
+    Marker(XrTrackedMarker),
+}
+
+/// An [`Event`] sent by the xr platform specific crate whenever the runtime detects, locates or loses an instance of an image or a marker.
+#[derive(Event, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrLocateImage {
+    /// The id of the detected instance assigned by the runtime, the same image can be detected multiple times.
+    pub instance: u64,
+    pub target: XrImageTarget,
+    /// The pose of the instance relative to the [`XrOrigin`].
+    pub transform: Transform,
+    pub tracking_state: XrTrackingState,
+}
+
+/// An [`Event`] sent whenever an instance of a [`XrTrackedImage`] or a [`XrTrackedMarker`] is detected or lost.
+#[derive(Event, Debug, Copy, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrImageEvent {
+    /// The instance was detected for the first time or again after it was lost.
+    Detected(Entity),
+    /// The tracking of the instance is lost, the entity keeps the last known pose.
+    Lost(Entity),
+}
+
+#[derive(Bundle)]
+pub struct XrTrackedImageBundle {
+    name: Name,
+    spatial_bundle: SpatialBundle,
+    xr_local: XrLocal,
+    xr_active: XrActive,
+    xr_tracking_state: XrTrackingState,
+    xr_tracked_image: XrTrackedImage,
+}
+
+impl XrTrackedImageBundle {
+    pub fn default(
+        image: XrTrackedImage,
+        transform: Transform,
+        tracking_state: XrTrackingState,
+    ) -> Self {
+        Self {
+            name: Name::new("XrTrackedImage_".to_string() + &image.name),
+            spatial_bundle: SpatialBundle::from_transform(transform),
+            xr_local: XrLocal,
+            xr_active: tracking_state.into(),
+            xr_tracking_state: tracking_state,
+            xr_tracked_image: image,
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct XrTrackedMarkerBundle {
+    name: Name,
+    spatial_bundle: SpatialBundle,
+    xr_local: XrLocal,
+    xr_active: XrActive,
+    xr_tracking_state: XrTrackingState,
+    xr_tracked_marker: XrTrackedMarker,
+}
+
+impl XrTrackedMarkerBundle {
+    pub fn default(
+        marker: XrTrackedMarker,
+        transform: Transform,
+        tracking_state: XrTrackingState,
+    ) -> Self {
+        Self {
+            name: Name::new(format!("XrTrackedMarker_{:?}_{}", marker.kind, marker.id)),
+            spatial_bundle: SpatialBundle::from_transform(transform),
+            xr_local: XrLocal,
+            xr_active: tracking_state.into(),
+            xr_tracking_state: tracking_state,
+            xr_tracked_marker: marker,
+        }
+    }
+}
+
+/// Spawns and updates the [`XrTrackedImage`] and [`XrTrackedMarker`] entities with the [`XrLocateImage`] events and sends the [`XrImageEvent`]s.
+pub fn apply_image_events(
+    mut commands: Commands,
+    origins: Query<Entity, (With<XrOrigin>, With<XrLocal>)>,
+    mut instances: Query<(&mut Transform, &mut XrTrackingState)>,
+    mut entities: Local<HashMap<u64, Entity>>,
+    mut locate_events: EventReader<XrLocateImage>,
+    mut image_events: EventWriter<XrImageEvent>,
+) {
+    // Forget the instances which were despawned by the app.
+    entities.retain(|_, entity| instances.contains(*entity));
+
+    for event in locate_events.read() {
+        if let Some(entity) = entities.get(&event.instance) {
+            let Ok((mut transform, mut tracking_state)) = instances.get_mut(*entity) else {
+                continue;
+            };
+            transform.set_if_neq(event.transform);
+            match (tracking_state.is_active(), event.tracking_state.is_active()) {
+                (false, true) => image_events.send(XrImageEvent::Detected(*entity)),
+                (true, false) => image_events.send(XrImageEvent::Lost(*entity)),
+                _ => {}
+            }
+            tracking_state.set_if_neq(event.tracking_state);
+            continue;
+        }
+
+        let Some(origin) = origins.iter().next() else {
+            continue;
+        };
+        let mut instance = match &event.target {
+            XrImageTarget::Image(image) => commands.spawn(XrTrackedImageBundle::default(
+                image.clone(),
+                event.transform,
+                event.tracking_state,
+            )),
+            XrImageTarget::Marker(marker) => commands.spawn(XrTrackedMarkerBundle::default(
+                marker.clone(),
+                event.transform,
+                event.tracking_state,
+            )),
+        };
+        instance.set_parent(origin);
+        let entity = instance.id();
+        entities.insert(event.instance, entity);
+        if event.tracking_state.is_active() {
+            image_events.send(XrImageEvent::Detected(entity));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::ManualEventReader;
+
+    use super::*;
+
+    fn locate(instance: u64, x: f32, tracking_state: XrTrackingState) -> XrLocateImage {
+        XrLocateImage {
+            instance,
+            target: XrImageTarget::Image(XrTrackedImage {
+                index: 0,
+                name: "poster".to_string(),
+                size: Vec2::new(0.6, 0.9),
+            }),
+            transform: Transform::from_xyz(x, 1.0, 0.0),
+            tracking_state,
+        }
+    }
+
+    fn image_events(app: &App, reader: &mut ManualEventReader<XrImageEvent>) -> Vec<XrImageEvent> {
+        reader
+            .read(app.world.resource::<Events<XrImageEvent>>())
+            .copied()
+            .collect()
+    }
+
+    fn instances(app: &mut App) -> Vec<Entity> {
+        app.world
+            .query_filtered::<Entity, With<XrTrackedImage>>()
+            .iter(&app.world)
+            .collect()
+    }
+
+    #[test]
+    fn detect_lose_and_despawn() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), XrImagePlugin));
+        let origin = app.world.spawn((XrOrigin::Room, XrLocal)).id();
+        let mut reader = ManualEventReader::<XrImageEvent>::default();
+
+        app.world
+            .send_event(locate(7, 1.0, XrTrackingState::Tracked));
+        app.update();
+        let [entity] = instances(&mut app)[..] else {
+            panic!("the detected image is not spawned");
+        };
+        assert_eq!(
+            image_events(&app, &mut reader),
+            vec![XrImageEvent::Detected(entity)]
+        );
+        assert_eq!(app.world.get::<Parent>(entity).unwrap().get(), origin);
+        assert_eq!(
+            app.world.get::<XrTrackedImage>(entity).unwrap().name,
+            "poster"
+        );
+
+        app.world.send_event(locate(7, 2.0, XrTrackingState::Lost));
+        app.update();
+        assert_eq!(instances(&mut app), vec![entity]);
+        assert_eq!(
+            image_events(&app, &mut reader),
+            vec![XrImageEvent::Lost(entity)]
+        );
+        assert_eq!(
+            *app.world.get::<XrTrackingState>(entity).unwrap(),
+            XrTrackingState::Lost
+        );
+        assert_eq!(
+            app.world.get::<Transform>(entity).unwrap().translation.x,
+            2.0
+        );
+
+        // A despawned instance is spawned again when it is located again.
+        app.world.despawn(entity);
+        app.world
+            .send_event(locate(7, 3.0, XrTrackingState::Tracked));
+        app.update();
+        let [respawned] = instances(&mut app)[..] else {
+            panic!("the located image is not spawned again");
+        };
+        assert_ne!(respawned, entity);
+        assert_eq!(
+            image_events(&app, &mut reader),
+            vec![XrImageEvent::Detected(respawned)]
+        );
+    }
+}
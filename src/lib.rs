@@ -8,26 +8,59 @@
 
 use bevy::prelude::*;
 
+#[cfg(feature = "recording")]
+pub mod animation;
+pub mod anchor;
+pub mod backend;
+pub mod boundary;
+pub mod camera;
 pub mod controller;
 pub mod controller_input;
+pub mod gaze;
 pub mod handedness;
 pub mod hands;
 pub mod head;
+pub mod image;
+pub mod locomotion;
+pub mod mesh;
+pub mod plane;
+pub mod plugin;
 pub mod pointer;
+pub mod projection;
+#[cfg(feature = "recording")]
+pub mod recording;
+pub mod remote;
 pub mod render;
+pub mod session;
+pub mod simulator;
 pub mod space;
+pub mod spectator;
 pub mod systems;
+pub mod teleport;
 pub mod tracked;
+pub mod validation;
 pub mod window;
 
-/// This [`Resource`] defines the type of xr experience.
-#[derive(Resource)]
+pub use plugin::{XrPlugin, XrPlugins, XrSet};
+
+/// This [`States`] defines the type of xr experience.
+///
+/// The state is set by the xr platform specific crate through a [`session::XrSessionEvent::ModeChanged`] event.
+/// Use [`OnEnter`] and [`OnExit`] to swap between AR, VR and a desktop fallback.
+#[derive(States, Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub enum XrMode {
     /// VR defines an experience where the viewer does not get to see or interact with the real world around him visually.
     VR,
     /// AR defines an experience where the viewer does get to see or interact with the real world around him.
     AR,
     /// None defines any situations without a xr experience such as the inline session of webxr or an openxr session without a headset.
+    #[default]
     None,
 }
 
@@ -39,7 +72,13 @@ pub enum XrMode {
 /// By exluding the component all components of this crate can be used for other entities that might use similae setups such as remote players.
 ///
 /// This component should be spawned with every entity that is managed by the xr platform specific crate.
-#[derive(Component)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component, Default)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub struct XrLocal;
 
 /// The defining [`Component`] which indicates that the entity is currently tracked.
@@ -49,20 +88,118 @@ pub struct XrLocal;
 ///
 /// This component enables systems to know if the current state is active.
 ///
-/// This component should be spawned with every entity that is managed by the xr platform specific crate.
+/// This component is derived from the [`XrTrackingState`] by the [`XrPlugin`] and should not be written by the xr platform specific crate.
 ///
-/// TODO: This could be an enum specifing the state of the xr object.
-#[derive(Component)]
+/// This component should be spawned with every entity that is managed by the xr platform specific crate.
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Component, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub struct XrActive(pub bool);
 
+/// The defining [`Component`] for the tracking state of a xr entity.
+///
+/// The xr platform specific crate updates this component whenever the runtime reports a different state for the entity.
+/// The [`XrActive`] component and the [`XrTrackingChanged`] events are derived from it.
+///
+/// This component should be spawned with every entity that is managed by the xr platform specific crate.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrTrackingState {
+    /// Position and orientation are tracked.
+    Tracked,
+    /// Only the orientation is tracked, the position is inferred by the runtime.
+    Inferred,
+    /// The tracking is lost and the transform holds the last known pose.
+    Lost,
+    /// The entity is not requested by the runtime, e.g. a view which is not rendered or a disconnected controller.
+    NotRequested,
+}
+
+/// Reflection inserts an entity which is not requested and then applies the reflected state.
+impl FromWorld for XrTrackingState {
+    fn from_world(_world: &mut World) -> Self {
+        Self::NotRequested
+    }
+}
+
+impl XrTrackingState {
+    /// Is the entity active? True for [`XrTrackingState::Tracked`] and [`XrTrackingState::Inferred`].
+    pub fn is_active(&self) -> bool {
+        matches!(self, XrTrackingState::Tracked | XrTrackingState::Inferred)
+    }
+}
+
+impl From<XrTrackingState> for XrActive {
+    fn from(value: XrTrackingState) -> Self {
+        XrActive(value.is_active())
+    }
+}
+
+/// An [`Event`] sent whenever the [`XrTrackingState`] of an entity transitions to another state.
+///
+/// No event is sent for the initial state of a newly spawned entity.
+#[derive(Event, Debug, Copy, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrTrackingChanged {
+    /// The entity whose tracking state changed.
+    pub entity: Entity,
+    /// The state before the transition.
+    pub previous: XrTrackingState,
+    /// The state after the transition.
+    pub current: XrTrackingState,
+}
+
 /// The defining [`Component`] which indicates that the entity is a xr managed view.
 ///
 /// This component should be spawned with every entity that is managed by the xr platform and has a camera that renders a tracked view such as an [`XrEye`] or an [`XrWindow`].
 ///
 /// The index of the view should be recorded in this component.
-#[derive(Component)]
+/// Every view index should be unique, [`XrView::eye`] and [`XrView::window`] allocate the indices for a rig of stereo view pairs followed by windows.
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub struct XrView(pub u8);
 
+impl XrView {
+    /// The view of the eye of the stereo view pair with the index, `2 * index` for the left and `2 * index + 1` for the right eye.
+    ///
+    /// Returns [`None`] if the view exceeds the index range.
+    pub fn eye(index: u8, handedness: handedness::Handedness) -> Option<Self> {
+        let offset = (handedness == handedness::Handedness::Right) as u8;
+        index
+            .checked_mul(2)
+            .and_then(|view| view.checked_add(offset))
+            .map(Self)
+    }
+
+    /// The view of the window with the index, following the views of the `eyes` stereo view pairs.
+    ///
+    /// Returns [`None`] if the view exceeds the index range.
+    pub fn window(eyes: u8, index: u8) -> Option<Self> {
+        eyes.checked_mul(2)
+            .and_then(|eye_views| eye_views.checked_add(index))
+            .map(Self)
+    }
+}
+
 pub trait IntoEnum<T> {
     fn into_enum() -> T;
 }
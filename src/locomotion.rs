@@ -0,0 +1,356 @@
+//! Smooth locomotion and turning of the [`XrOrigin`] with the sticks of the controllers.
+//!
+//! The stick of the [`XrLocomotionSettings::move_controller`] translates the origin along the floor relative to the forward of the head or the controller.
+//! The stick of the [`XrLocomotionSettings::turn_controller`] rotates the origin around the vertical axis through the [`XrHead`], so the user turns in place.
+//!
+//! Both are computed in world space and converted into the space of the parent of the origin.
+
+use bevy::{math::Affine3A, prelude::*};
+
+use crate::{
+    controller::XrController,
+    controller_input::{AnalogInput, XrControllerAxis, XrControllerAxisType},
+    head::{XrHead, XrHeadset},
+    space::{XrOrigin, XrWorldScale},
+    XrLocal, XrSet,
+};
+
+pub struct XrLocomotionPlugin;
+
+impl Plugin for XrLocomotionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrLocomotionSettings>().add_systems(
+            Update,
+            (move_origins, turn_origins)
+                .chain()
+                .in_set(XrSet::Interaction),
+        );
+
+        app.register_type::<XrLocomotionSettings>()
+            .register_type::<XrMoveReference>()
+            .register_type::<XrTurnMode>();
+    }
+}
+
+/// The forward direction of the locomotion.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Debug, Default, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrMoveReference {
+    /// Moves towards the direction the head is facing.
+    #[default]
+    Head,
+    /// Moves towards the direction the moving controller is pointing.
+    Controller,
+}
+
+/// How the origin is turned.
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrTurnMode {
+    /// Turns by the angle in radians once the stick is pushed beyond the threshold, the stick has to return into the dead zone before the next turn.
+    Snap { angle: f32, threshold: f32 },
+    /// Turns continuously with the speed in radians per second at full deflection.
+    Smooth { speed: f32 },
+}
+
+/// Settings of the [`XrLocomotionPlugin`].
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Debug, Default)]
+pub struct XrLocomotionSettings {
+    /// The controller whose stick moves the origin, [`None`] disables moving.
+    pub move_controller: Option<XrController>,
+    /// The controller whose stick turns the origin, [`None`] disables turning.
+    pub turn_controller: Option<XrController>,
+    pub move_reference: XrMoveReference,
+    /// The speed at full deflection in meters of the tracking space per second, see [`XrWorldScale`].
+    pub move_speed: f32,
+    pub turn_mode: XrTurnMode,
+    /// The deflection of the sticks below which they are ignored.
+    pub dead_zone: f32,
+}
+
+impl Default for XrLocomotionSettings {
+    fn default() -> Self {
+        Self {
+            move_controller: Some(XrController::Left),
+            turn_controller: Some(XrController::Right),
+            move_reference: XrMoveReference::Head,
+            move_speed: 2.0,
+            turn_mode: XrTurnMode::Snap {
+                angle: 30f32.to_radians(),
+                threshold: 0.7,
+            },
+            dead_zone: 0.2,
+        }
+    }
+}
+
+/// Returns the stick of the controller with the radial dead zone applied and rescaled to the remaining range.
+fn stick(
+    axes: &AnalogInput<XrControllerAxis>,
+    xr_controller: XrController,
+    dead_zone: f32,
+) -> Vec2 {
+    let value = Vec2::new(
+        axes.get(XrControllerAxis::new(
+            xr_controller,
+            XrControllerAxisType::StickX,
+        ))
+        .unwrap_or_default(),
+        axes.get(XrControllerAxis::new(
+            xr_controller,
+            XrControllerAxisType::StickY,
+        ))
+        .unwrap_or_default(),
+    );
+    let length = value.length();
+    if length <= dead_zone {
+        return Vec2::ZERO;
+    }
+    value / length * ((length - dead_zone) / (1.0 - dead_zone).max(f32::EPSILON)).min(1.0)
+}
+
+/// The heads and headsets with their parent.
+type XrHeadQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Parent, &'static GlobalTransform, Has<XrHead>),
+    Or<(With<XrHead>, With<XrHeadset>)>,
+>;
+
+/// Returns the inverse of the transform of the parent, which converts from world space into the space of the origin's transform.
+fn parent_inverse(
+    parent: Option<&Parent>,
+    global_transforms: &Query<&GlobalTransform>,
+) -> Affine3A {
+    parent
+        .and_then(|parent| global_transforms.get(parent.get()).ok())
+        .map_or(Affine3A::IDENTITY, |parent| parent.affine().inverse())
+}
+
+/// Returns the head of the origin and falls back to the headset.
+fn head_of(origin: Entity, heads: &XrHeadQuery) -> Option<GlobalTransform> {
+    heads
+        .iter()
+        .filter(|(parent, _, _)| parent.get() == origin)
+        .max_by_key(|(_, _, is_head)| *is_head)
+        .map(|(_, transform, _)| *transform)
+}
+
+/// Moves the local [`XrOrigin`]s with the stick of the [`XrLocomotionSettings::move_controller`].
+#[allow(clippy::type_complexity)]
+pub fn move_origins(
+    settings: Res<XrLocomotionSettings>,
+    time: Res<Time>,
+    axes: Res<AnalogInput<XrControllerAxis>>,
+    mut origins: Query<
+        (
+            Entity,
+            &mut Transform,
+            Option<&Parent>,
+            Option<&XrWorldScale>,
+        ),
+        (With<XrOrigin>, With<XrLocal>),
+    >,
+    heads: XrHeadQuery,
+    controllers: Query<(&Parent, &GlobalTransform, &XrController)>,
+    global_transforms: Query<&GlobalTransform>,
+) {
+    let Some(move_controller) = settings.move_controller else {
+        return;
+    };
+    let input = stick(&axes, move_controller, settings.dead_zone);
+    if input == Vec2::ZERO {
+        return;
+    }
+
+    for (origin, mut transform, parent, world_scale) in origins.iter_mut() {
+        let reference = match settings.move_reference {
+            XrMoveReference::Head => head_of(origin, &heads),
+            XrMoveReference::Controller => controllers
+                .iter()
+                .find(|(parent, _, xr_controller)| {
+                    parent.get() == origin && **xr_controller == move_controller
+                })
+                .map(|(_, transform, _)| *transform),
+        };
+        let Some(reference) = reference else {
+            continue;
+        };
+
+        let forward =
+            Vec3::new(reference.forward().x, 0.0, reference.forward().z).normalize_or_zero();
+        let right = Vec3::new(reference.right().x, 0.0, reference.right().z).normalize_or_zero();
+        let scale = world_scale.map_or(1.0, |world_scale| world_scale.0);
+        let delta = (right * input.x + forward * input.y)
+            * settings.move_speed
+            * scale
+            * time.delta_seconds();
+        transform.translation +=
+            parent_inverse(parent, &global_transforms).transform_vector3(delta);
+    }
+}
+
+/// Turns the local [`XrOrigin`]s around their head with the stick of the [`XrLocomotionSettings::turn_controller`].
+#[allow(clippy::type_complexity)]
+pub fn turn_origins(
+    settings: Res<XrLocomotionSettings>,
+    time: Res<Time>,
+    axes: Res<AnalogInput<XrControllerAxis>>,
+    mut snap_ready: Local<bool>,
+    mut origins: Query<
+        (Entity, &mut Transform, &GlobalTransform, Option<&Parent>),
+        (With<XrOrigin>, With<XrLocal>),
+    >,
+    heads: XrHeadQuery,
+    global_transforms: Query<&GlobalTransform>,
+) {
+    let Some(turn_controller) = settings.turn_controller else {
+        return;
+    };
+    let input = stick(&axes, turn_controller, settings.dead_zone).x;
+
+    let angle = match settings.turn_mode {
+        XrTurnMode::Snap { angle, threshold } => {
+            if input == 0.0 {
+                *snap_ready = true;
+            }
+            if *snap_ready && input.abs() >= threshold {
+                *snap_ready = false;
+                angle * input.signum()
+            } else {
+                0.0
+            }
+        }
+        XrTurnMode::Smooth { speed } => speed * input * time.delta_seconds(),
+    };
+    if angle == 0.0 {
+        return;
+    }
+
+    for (origin, mut transform, origin_global, parent) in origins.iter_mut() {
+        // The pivot relative to the origin, the transform may have been moved since it was propagated.
+        let pivot = head_of(origin, &heads).map_or(Vec3::ZERO, |head| {
+            origin_global
+                .affine()
+                .inverse()
+                .transform_point3(head.translation())
+        });
+        let pivot = transform.transform_point(pivot);
+        let up = parent_inverse(parent, &global_transforms)
+            .transform_vector3(Vec3::Y)
+            .normalize_or_zero();
+        // A positive stick turns to the right, which is a negative rotation around up.
+        transform.rotate_around(pivot, Quat::from_axis_angle(up, -angle));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_stick(app: &mut App, x: f32) {
+        app.world
+            .resource_mut::<AnalogInput<XrControllerAxis>>()
+            .set(
+                XrControllerAxis::new(XrController::Right, XrControllerAxisType::StickX),
+                x,
+            );
+    }
+
+    fn head_pose(app: &App, head: Entity) -> (Vec3, f32) {
+        let transform = app.world.get::<GlobalTransform>(head).unwrap();
+        let yaw = transform
+            .compute_transform()
+            .rotation
+            .to_euler(EulerRot::YXZ)
+            .0;
+        (transform.translation(), yaw)
+    }
+
+    #[test]
+    fn stick_dead_zone() {
+        let mut axes = AnalogInput::<XrControllerAxis>::default();
+        let x = XrControllerAxis::new(XrController::Left, XrControllerAxisType::StickX);
+        let y = XrControllerAxis::new(XrController::Left, XrControllerAxisType::StickY);
+
+        axes.set(x, 0.1);
+        axes.set(y, 0.1);
+        assert_eq!(stick(&axes, XrController::Left, 0.2), Vec2::ZERO);
+
+        axes.set(x, 0.6);
+        axes.set(y, 0.0);
+        assert!(stick(&axes, XrController::Left, 0.2).abs_diff_eq(Vec2::new(0.5, 0.0), 1e-6));
+
+        axes.set(x, 0.0);
+        axes.set(y, -1.0);
+        assert!(stick(&axes, XrController::Left, 0.2).abs_diff_eq(Vec2::NEG_Y, 1e-6));
+        assert_eq!(stick(&axes, XrController::Right, 0.2), Vec2::ZERO);
+    }
+
+    #[test]
+    fn snap_turn_around_head() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, TransformPlugin, HierarchyPlugin))
+            .init_resource::<XrLocomotionSettings>()
+            .init_resource::<AnalogInput<XrControllerAxis>>()
+            .add_systems(Update, (move_origins, turn_origins).chain());
+
+        let parent = app
+            .world
+            .spawn(SpatialBundle::from_transform(
+                Transform::from_xyz(5.0, 1.0, -2.0)
+                    .with_rotation(Quat::from_rotation_y(1.0))
+                    .with_scale(Vec3::splat(2.0)),
+            ))
+            .id();
+        let origin = app
+            .world
+            .spawn((
+                XrOrigin::Room,
+                XrLocal,
+                SpatialBundle::from_transform(
+                    Transform::from_xyz(1.0, 0.0, 2.0).with_rotation(Quat::from_rotation_y(0.5)),
+                ),
+            ))
+            .set_parent(parent)
+            .id();
+        let head = app
+            .world
+            .spawn((
+                XrHead,
+                SpatialBundle::from_transform(Transform::from_xyz(0.5, 1.6, 0.3)),
+            ))
+            .set_parent(origin)
+            .id();
+        app.update();
+        let (position, yaw) = head_pose(&app, head);
+
+        set_stick(&mut app, 1.0);
+        app.update();
+        let (turned_position, turned_yaw) = head_pose(&app, head);
+        assert!(turned_position.abs_diff_eq(position, 1e-4));
+        assert!((turned_yaw - (yaw - 30f32.to_radians())).abs() < 1e-4);
+
+        // Holding the stick does not turn again until it returns into the dead zone.
+        app.update();
+        assert!((head_pose(&app, head).1 - turned_yaw).abs() < 1e-6);
+        set_stick(&mut app, 0.1);
+        app.update();
+        set_stick(&mut app, -1.0);
+        app.update();
+        let (position_back, yaw_back) = head_pose(&app, head);
+        assert!(position_back.abs_diff_eq(position, 1e-4));
+        assert!((yaw_back - yaw).abs() < 1e-4);
+    }
+}
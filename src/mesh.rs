@@ -0,0 +1,356 @@
+//! Environment meshes reconstructed by scene understanding runtimes, so AR content can collide with and be occluded by the real world.
+//!
+//! A [`XrTrackedMesh`] is split into [`XrMeshChunk`]s which the runtime updates independently while it refines the reconstruction.
+//! The xr platform specific crate inserts and removes chunks with [`XrTrackedMesh::insert_chunk`] and [`XrTrackedMesh::remove_chunk`].
+//!
+//! The [`XrMeshPlugin`] converts every changed chunk into a [`Mesh`] asset on a child entity with a [`XrTrackedMeshChunk`],
+//! so apps only have to add a material or a collider to these entities.
+
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    utils::{HashMap, HashSet},
+};
+
+use crate::{XrActive, XrLocal, XrTrackingState};
+
+pub struct XrMeshPlugin;
+
+impl Plugin for XrMeshPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            update_mesh_chunks.run_if(resource_exists::<Assets<Mesh>>()),
+        );
+
+        app.register_type::<XrTrackedMesh>()
+            .register_type::<XrTrackedMeshChunk>()
+            .register_type::<XrMeshChunk>()
+            .register_type::<XrMeshLabel>();
+    }
+}
+
+/// The semantic label of a face of a [`XrMeshChunk`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Debug, Default, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrMeshLabel {
+    /// The runtime does not classify faces or could not classify this face.
+    #[default]
+    Unknown,
+    Floor,
+    Ceiling,
+    Wall,
+    Table,
+    Seat,
+    Door,
+    Window,
+    Furniture,
+    Other,
+}
+
+/// A part of a [`XrTrackedMesh`] which is updated by the runtime as a whole.
+///
+/// The positions are relative to the entity of the [`XrTrackedMesh`], each three indices form a face with the label of the same index.
+#[derive(Debug, Default, Clone, PartialEq, Reflect)]
+#[reflect(Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrMeshChunk {
+    pub positions: Vec<Vec3>,
+    /// The indices of the counter clockwise faces.
+    pub indices: Vec<u32>,
+    /// The label of each face, empty if the runtime does not classify faces.
+    pub labels: Vec<XrMeshLabel>,
+}
+
+impl XrMeshChunk {
+    pub fn new(positions: Vec<Vec3>, indices: Vec<u32>, labels: Vec<XrMeshLabel>) -> Self {
+        Self {
+            positions,
+            indices,
+            labels,
+        }
+    }
+
+    /// The label of the face, [`XrMeshLabel::Unknown`] for faces without a label.
+    pub fn label(&self, face: usize) -> XrMeshLabel {
+        self.labels.get(face).copied().unwrap_or_default()
+    }
+
+    /// Creates a [`Mesh`] of all faces.
+    pub fn mesh(&self) -> Mesh {
+        self.mesh_filtered(|_| true)
+    }
+
+    /// Creates a [`Mesh`] of the faces whose label passes the filter, e.g. only walls and furniture for physics.
+    pub fn mesh_filtered(&self, filter: impl Fn(XrMeshLabel) -> bool) -> Mesh {
+        let indices: Vec<u32> = self
+            .indices
+            .chunks_exact(3)
+            .enumerate()
+            .filter(|(face, _)| filter(self.label(*face)))
+            .flat_map(|(_, face)| face.iter().copied())
+            .collect();
+
+        // Smooth normals weighted by the area of the adjacent faces.
+        let mut normals = vec![Vec3::ZERO; self.positions.len()];
+        for face in indices.chunks_exact(3) {
+            let [a, b, c] = [face[0], face[1], face[2]].map(|index| index as usize);
+            let normal = (self.positions[b] - self.positions[a])
+                .cross(self.positions[c] - self.positions[a]);
+            normals[a] += normal;
+            normals[b] += normal;
+            normals[c] += normal;
+        }
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            self.positions
+                .iter()
+                .map(|position| position.to_array())
+                .collect::<Vec<_>>(),
+        );
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_NORMAL,
+            normals
+                .iter()
+                .map(|normal| normal.normalize_or_zero().to_array())
+                .collect::<Vec<_>>(),
+        );
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh
+    }
+}
+
+/// The defining [`Component`] for environment meshes reconstructed by the runtime.
+///
+/// The mesh is made of [`XrMeshChunk`]s identified by the id the runtime assigns to them.
+/// Changed chunks are remembered until the [`XrMeshPlugin`] converts them into [`Mesh`] assets.
+///
+/// Mesh entities should be parented to a [`crate::space::XrOrigin`] entity and include a [`XrTrackingState`] and a [`XrActive`].
+#[derive(Component, Debug, Default, Clone, Reflect)]
+#[reflect(Component, Debug, Default)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrTrackedMesh {
+    chunks: HashMap<u64, XrMeshChunk>,
+    #[reflect(ignore)]
+    #[cfg_attr(feature = "serialize", serde(skip))]
+    updated: HashSet<u64>,
+    #[reflect(ignore)]
+    #[cfg_attr(feature = "serialize", serde(skip))]
+    removed: HashSet<u64>,
+}
+
+impl XrTrackedMesh {
+    /// Inserts a new chunk or replaces the chunk with the same id.
+    pub fn insert_chunk(&mut self, id: u64, chunk: XrMeshChunk) {
+        self.chunks.insert(id, chunk);
+        self.removed.remove(&id);
+        self.updated.insert(id);
+    }
+
+    /// Removes the chunk and returns it.
+    pub fn remove_chunk(&mut self, id: u64) -> Option<XrMeshChunk> {
+        let chunk = self.chunks.remove(&id)?;
+        self.updated.remove(&id);
+        self.removed.insert(id);
+        Some(chunk)
+    }
+
+    pub fn chunk(&self, id: u64) -> Option<&XrMeshChunk> {
+        self.chunks.get(&id)
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = (u64, &XrMeshChunk)> {
+        self.chunks.iter().map(|(id, chunk)| (*id, chunk))
+    }
+
+    /// Creates a [`Mesh`] of all chunks.
+    pub fn mesh(&self) -> Mesh {
+        self.mesh_filtered(|_| true)
+    }
+
+    /// Creates a [`Mesh`] of all chunks, see [`XrMeshChunk::mesh_filtered`].
+    pub fn mesh_filtered(&self, filter: impl Fn(XrMeshLabel) -> bool) -> Mesh {
+        let mut combined = XrMeshChunk::default();
+        for chunk in self.chunks.values() {
+            let offset = combined.positions.len() as u32;
+            let faces = chunk.indices.len() / 3;
+            combined.positions.extend(&chunk.positions);
+            combined
+                .indices
+                .extend(chunk.indices.iter().map(|index| index + offset));
+            combined
+                .labels
+                .extend((0..faces).map(|face| chunk.label(face)));
+        }
+        combined.mesh_filtered(filter)
+    }
+}
+
+#[derive(Bundle)]
+pub struct XrTrackedMeshBundle {
+    name: Name,
+    spatial_bundle: SpatialBundle,
+    xr_local: XrLocal,
+    xr_active: XrActive,
+    xr_tracking_state: XrTrackingState,
+    xr_tracked_mesh: XrTrackedMesh,
+}
+
+impl XrTrackedMeshBundle {
+    pub fn default(transform: Transform) -> Self {
+        Self {
+            name: Name::new("XrTrackedMesh"),
+            spatial_bundle: SpatialBundle::from_transform(transform),
+            xr_local: XrLocal,
+            xr_active: XrActive(true),
+            xr_tracking_state: XrTrackingState::Tracked,
+            xr_tracked_mesh: XrTrackedMesh::default(),
+        }
+    }
+}
+
+/// The defining [`Component`] for the child entities of a [`XrTrackedMesh`] which hold the [`Mesh`] of a [`XrMeshChunk`].
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrTrackedMeshChunk(pub u64);
+
+/// Spawns, updates and despawns the [`XrTrackedMeshChunk`] entities of the changed [`XrMeshChunk`]s.
+pub fn update_mesh_chunks(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut tracked_meshes: Query<
+        (Entity, &mut XrTrackedMesh, Option<&Children>),
+        Changed<XrTrackedMesh>,
+    >,
+    chunks: Query<(&XrTrackedMeshChunk, &Handle<Mesh>)>,
+) {
+    for (entity, mut tracked_mesh, children) in tracked_meshes.iter_mut() {
+        // Taking the changes must not mark the mesh as changed again.
+        let tracked_mesh = tracked_mesh.bypass_change_detection();
+        let updated = std::mem::take(&mut tracked_mesh.updated);
+        let removed = std::mem::take(&mut tracked_mesh.removed);
+
+        let mut existing = HashMap::default();
+        for child in children.into_iter().flatten() {
+            if let Ok((chunk, handle)) = chunks.get(*child) {
+                existing.insert(chunk.0, (*child, handle.id()));
+            }
+        }
+
+        for id in removed {
+            if let Some((child, _)) = existing.get(&id) {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+        for id in updated {
+            let Some(chunk) = tracked_mesh.chunks.get(&id) else {
+                continue;
+            };
+            if let Some((_, handle)) = existing.get(&id) {
+                meshes.insert(*handle, chunk.mesh());
+            } else {
+                commands.entity(entity).with_children(|parent| {
+                    parent.spawn((
+                        Name::new("XrTrackedMeshChunk_".to_string() + &id.to_string()),
+                        SpatialBundle::default(),
+                        XrTrackedMeshChunk(id),
+                        meshes.add(chunk.mesh()),
+                    ));
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle(size: f32) -> XrMeshChunk {
+        XrMeshChunk::new(
+            vec![Vec3::ZERO, Vec3::X * size, Vec3::Z * size],
+            vec![0, 2, 1],
+            Vec::new(),
+        )
+    }
+
+    fn chunk_meshes(app: &mut App) -> HashMap<u64, (AssetId<Mesh>, usize)> {
+        let mut chunks = app.world.query::<(&XrTrackedMeshChunk, &Handle<Mesh>)>();
+        let meshes = app.world.resource::<Assets<Mesh>>();
+        chunks
+            .iter(&app.world)
+            .map(|(chunk, handle)| {
+                let mesh = meshes.get(handle).unwrap();
+                (chunk.0, (handle.id(), mesh.count_vertices()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chunks_produce_meshes() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), XrMeshPlugin))
+            .init_asset::<Mesh>();
+        let entity = app
+            .world
+            .spawn(XrTrackedMeshBundle::default(Transform::IDENTITY))
+            .id();
+
+        let mut tracked_mesh = app.world.get_mut::<XrTrackedMesh>(entity).unwrap();
+        tracked_mesh.insert_chunk(1, triangle(1.0));
+        let mut quad = triangle(2.0);
+        quad.positions.push(Vec3::new(2.0, 0.0, 2.0));
+        quad.indices.extend([1, 2, 3]);
+        tracked_mesh.insert_chunk(2, quad);
+        app.update();
+
+        let meshes = chunk_meshes(&mut app);
+        assert_eq!(meshes.len(), 2);
+        assert_eq!(meshes[&1].1, 3);
+        assert_eq!(meshes[&2].1, 4);
+        assert_eq!(app.world.resource::<Assets<Mesh>>().len(), 2);
+
+        // Replacing a chunk updates its mesh asset in place.
+        let mut bigger = triangle(1.0);
+        bigger.positions.extend([Vec3::ONE, Vec3::Y]);
+        app.world
+            .get_mut::<XrTrackedMesh>(entity)
+            .unwrap()
+            .insert_chunk(1, bigger);
+        app.update();
+        let updated = chunk_meshes(&mut app);
+        assert_eq!(updated[&1], (meshes[&1].0, 5));
+
+        // Removing a chunk despawns its entity and its mesh asset is dropped.
+        app.world
+            .get_mut::<XrTrackedMesh>(entity)
+            .unwrap()
+            .remove_chunk(2);
+        app.update();
+        app.update();
+        let remaining = chunk_meshes(&mut app);
+        assert_eq!(remaining.keys().copied().collect::<Vec<_>>(), vec![1]);
+        assert_eq!(app.world.resource::<Assets<Mesh>>().len(), 1);
+        assert_eq!(app.world.get::<Children>(entity).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,335 @@
+//! Planes detected in the real world by AR runtimes, such as floors, walls and tables.
+//!
+//! A [`XrTrackedPlane`] holds the boundary polygon of a detected plane, its [`XrPlaneOrientation`] and its semantic [`XrPlaneLabel`].
+//! Plane entities are spawned, updated and despawned by the xr platform specific crate as children of the [`crate::space::XrOrigin`].
+//! The [`XrPlanePlugin`] derives a [`XrPlaneEvent`] from these changes, so apps do not have to track them.
+//!
+//! [`XrTrackedPlane::mesh`] creates a [`Mesh`] of the polygon for occlusion or physics.
+
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+
+use crate::{XrActive, XrLocal, XrSet, XrTrackingState};
+
+pub struct XrPlanePlugin;
+
+impl Plugin for XrPlanePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<XrPlaneEvent>()
+            .add_systems(PreUpdate, send_plane_events.in_set(XrSet::Derive));
+
+        app.register_type::<XrTrackedPlane>()
+            .register_type::<XrPlaneOrientation>()
+            .register_type::<XrPlaneLabel>()
+            .register_type::<XrPlaneEvent>();
+    }
+}
+
+/// The orientation of a [`XrTrackedPlane`] as reported by the runtime.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrPlaneOrientation {
+    /// A horizontal plane facing up, e.g. a floor or a table.
+    HorizontalUp,
+    /// A horizontal plane facing down, e.g. a ceiling.
+    HorizontalDown,
+    /// A vertical plane, e.g. a wall.
+    Vertical,
+    /// Any other orientation, e.g. a ramp.
+    Arbitrary,
+}
+
+/// The semantic label of a [`XrTrackedPlane`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Debug, Default, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrPlaneLabel {
+    /// The runtime does not classify planes or could not classify this plane.
+    #[default]
+    Unknown,
+    Floor,
+    Ceiling,
+    Wall,
+    Table,
+    Seat,
+    Door,
+    Window,
+    Other,
+}
+
+/// The defining [`Component`] for planes detected by the runtime.
+/// The `x` and `y` of each point of the polygon are the `x` and `z` coordinates relative to the entity, the plane faces the local `y` axis.
+///
+/// The polygon may be concave and is closed implicitly, the last point connects to the first.
+///
+/// Plane entities should be parented to a [`crate::space::XrOrigin`] entity and include a [`XrTrackingState`] and a [`XrActive`].
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrTrackedPlane {
+    pub polygon: Vec<Vec2>,
+    pub orientation: XrPlaneOrientation,
+    pub label: XrPlaneLabel,
+}
+
+/// Reflection inserts an empty plane of arbitrary orientation and then applies the reflected one.
+impl FromWorld for XrTrackedPlane {
+    fn from_world(_world: &mut World) -> Self {
+        Self::new(
+            Vec::new(),
+            XrPlaneOrientation::Arbitrary,
+            XrPlaneLabel::Unknown,
+        )
+    }
+}
+
+impl XrTrackedPlane {
+    pub fn new(polygon: Vec<Vec2>, orientation: XrPlaneOrientation, label: XrPlaneLabel) -> Self {
+        Self {
+            polygon,
+            orientation,
+            label,
+        }
+    }
+
+    /// The area of the polygon in square meters.
+    pub fn area(&self) -> f32 {
+        signed_area(&self.polygon).abs()
+    }
+
+    /// Creates a [`Mesh`] of the polygon facing the local `y` axis, the uvs are the local `x` and `z` coordinates in meters.
+    pub fn mesh(&self) -> Mesh {
+        let positions: Vec<[f32; 3]> = self
+            .polygon
+            .iter()
+            .map(|point| [point.x, 0.0, point.y])
+            .collect();
+        let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+        let uvs: Vec<[f32; 2]> = self.polygon.iter().map(|point| point.to_array()).collect();
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        mesh.set_indices(Some(Indices::U32(triangulate(&self.polygon))));
+        mesh
+    }
+}
+
+/// The signed area of the polygon, positive for counter clockwise polygons in the `x` `y` plane.
+fn signed_area(polygon: &[Vec2]) -> f32 {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(start, end)| start.perp_dot(*end))
+        .sum::<f32>()
+        / 2.0
+}
+
+/// Triangulates the polygon by ear clipping, the triangles face the `y` axis of the `x` `z` plane.
+fn triangulate(polygon: &[Vec2]) -> Vec<u32> {
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+    if signed_area(polygon) < 0.0 {
+        remaining.reverse();
+    }
+
+    let mut indices = Vec::new();
+    let mut push = |a: usize, b: usize, c: usize| {
+        // Counter clockwise in `x` `y` is clockwise seen from the `y` axis of the `x` `z` plane.
+        indices.extend([a as u32, c as u32, b as u32]);
+    };
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&i| {
+            let [a, b, c] = [
+                remaining[(i + count - 1) % count],
+                remaining[i],
+                remaining[(i + 1) % count],
+            ]
+            .map(|index| polygon[index]);
+            (b - a).perp_dot(c - b) > 0.0
+                && remaining.iter().all(|&other| {
+                    let point = polygon[other];
+                    point == a
+                        || point == b
+                        || point == c
+                        || (b - a).perp_dot(point - a) < 0.0
+                        || (c - b).perp_dot(point - b) < 0.0
+                        || (a - c).perp_dot(point - c) < 0.0
+                })
+        });
+        // A degenerate polygon without ears is closed with a fan.
+        let i = ear.unwrap_or(0);
+        push(
+            remaining[(i + count - 1) % count],
+            remaining[i],
+            remaining[(i + 1) % count],
+        );
+        remaining.remove(i);
+    }
+    if let [a, b, c] = remaining[..] {
+        push(a, b, c);
+    }
+    indices
+}
+
+#[derive(Bundle)]
+pub struct XrTrackedPlaneBundle {
+    name: Name,
+    spatial_bundle: SpatialBundle,
+    xr_local: XrLocal,
+    xr_active: XrActive,
+    xr_tracking_state: XrTrackingState,
+    xr_tracked_plane: XrTrackedPlane,
+}
+
+impl XrTrackedPlaneBundle {
+    pub fn default(plane: XrTrackedPlane, transform: Transform) -> Self {
+        Self {
+            name: Name::new(format!("XrTrackedPlane_{:?}", plane.label)),
+            spatial_bundle: SpatialBundle::from_transform(transform),
+            xr_local: XrLocal,
+            xr_active: XrActive(true),
+            xr_tracking_state: XrTrackingState::Tracked,
+            xr_tracked_plane: plane,
+        }
+    }
+}
+
+/// An [`Event`] sent whenever a [`XrTrackedPlane`] is added, updated or removed.
+#[derive(Event, Debug, Copy, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrPlaneEvent {
+    /// The plane was detected.
+    Added(Entity),
+    /// The polygon, orientation, label or pose of the plane changed.
+    Updated(Entity),
+    /// The plane is not detected anymore.
+    Removed(Entity),
+}
+
+/// Sends the [`XrPlaneEvent`]s for added, changed and removed [`XrTrackedPlane`]s.
+#[allow(clippy::type_complexity)]
+pub fn send_plane_events(
+    planes: Query<(Entity, Ref<XrTrackedPlane>), Or<(Changed<XrTrackedPlane>, Changed<Transform>)>>,
+    mut removed: RemovedComponents<XrTrackedPlane>,
+    mut plane_events: EventWriter<XrPlaneEvent>,
+) {
+    for entity in removed.read() {
+        plane_events.send(XrPlaneEvent::Removed(entity));
+    }
+    for (entity, plane) in planes.iter() {
+        if plane.is_added() {
+            plane_events.send(XrPlaneEvent::Added(entity));
+        } else {
+            plane_events.send(XrPlaneEvent::Updated(entity));
+        }
+    }
+}
+
+/// Draws the polygons of the [`XrTrackedPlane`]s.
+pub fn draw_plane_gizmos(planes: Query<(&XrTrackedPlane, &GlobalTransform)>, mut gizmos: Gizmos) {
+    for (plane, transform) in planes.iter() {
+        gizmos.linestrip(
+            plane
+                .polygon
+                .iter()
+                .chain(plane.polygon.first())
+                .map(|point| transform.transform_point(Vec3::new(point.x, 0.0, point.y))),
+            Color::YELLOW,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An L-shape with the notch at the top right, counter clockwise in `x` `y`.
+    fn l_shape() -> Vec<Vec2> {
+        vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(4.0, 0.0),
+            Vec2::new(4.0, 2.0),
+            Vec2::new(2.0, 2.0),
+            Vec2::new(2.0, 4.0),
+            Vec2::new(0.0, 4.0),
+        ]
+    }
+
+    /// The signed areas of the triangles in `x` `y`.
+    fn triangle_areas(polygon: &[Vec2], indices: &[u32]) -> Vec<f32> {
+        indices
+            .chunks(3)
+            .map(|triangle| {
+                signed_area(
+                    &triangle
+                        .iter()
+                        .map(|&i| polygon[i as usize])
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn triangulate_concave() {
+        let polygon = l_shape();
+        let indices = triangulate(&polygon);
+        assert_eq!(indices.len(), 3 * (polygon.len() - 2));
+
+        let areas = triangle_areas(&polygon, &indices);
+        // The triangles face the `y` axis of the `x` `z` plane, so they are clockwise in `x` `y`.
+        assert!(areas.iter().all(|area| *area < 0.0));
+        // Triangles across the notch would cover more than the polygon.
+        assert_eq!(areas.iter().sum::<f32>(), -signed_area(&polygon));
+    }
+
+    #[test]
+    fn triangulate_clockwise() {
+        let mut polygon = l_shape();
+        polygon.reverse();
+        let indices = triangulate(&polygon);
+        assert_eq!(indices.len(), 3 * (polygon.len() - 2));
+
+        let areas = triangle_areas(&polygon, &indices);
+        assert!(areas.iter().all(|area| *area < 0.0));
+        assert_eq!(areas.iter().sum::<f32>(), signed_area(&polygon));
+    }
+
+    #[test]
+    fn triangulate_degenerate() {
+        let polygon = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(3.0, 0.0),
+        ];
+        let indices = triangulate(&polygon);
+        assert_eq!(indices.len(), 6);
+        assert!(indices.iter().all(|&i| (i as usize) < polygon.len()));
+        assert!(triangle_areas(&polygon, &indices)
+            .iter()
+            .all(|area| *area == 0.0));
+    }
+}
@@ -0,0 +1,191 @@
+//! [`Plugin`]s wiring the components and systems of this crate into an [`App`].
+
+use bevy::{app::PluginGroupBuilder, input::InputSystem, prelude::*, transform::TransformSystem};
+
+use crate::{
+    boundary::draw_boundary_gizmos,
+    camera::XrCameraSettingsPlugin,
+    controller::XrController,
+    controller_input::XrControllerInputPlugin,
+    handedness::{Handedness, LeftHanded, RightHanded},
+    hands::{
+        finger::{Finger, Index, Little, Middle, Ring, Thumb},
+        finger_joint::{
+            DistalPhalanx, FingerJoint, IntermediatePhalanx, Metacarpal, ProximalPhalanx, Tip,
+        },
+        hand_joint::{Forearm, Palm, Wrist},
+        Hand, HandJointRadius,
+    },
+    head::{XrEye, XrHead, XrHeadset, XrNeckModel},
+    plane::draw_plane_gizmos,
+    pointer::XrPointer,
+    projection::XrProjectionPlugin,
+    session::{xr_session_event_system, XrSessionEvent, XrSessionState},
+    space::{
+        apply_world_scale, recenter_origins, switch_origins, XrOrigin, XrOriginSwitchRequest,
+        XrRecenterRequest, XrReferenceSpaceChanged, XrSpaceSettings, XrWorldScale,
+    },
+    systems::{
+        apply_neck_models, draw_controller_gizmos, draw_hand_gizmos, send_tracking_changed,
+        substitute_local_palm, update_active,
+    },
+    tracked::XrTrackedObject,
+    window::XrWindow,
+    XrActive, XrLocal, XrMode, XrTrackingChanged, XrTrackingState, XrView,
+};
+
+/// All [`Plugin`]s of this crate.
+///
+/// Contains the [`XrPlugin`], the [`XrControllerInputPlugin`], the [`XrProjectionPlugin`], the [`XrCameraSettingsPlugin`] and the [`XrGizmoPlugin`].
+/// The [`XrGizmoPlugin`] requires the [`bevy::gizmos::GizmoPlugin`] and can be disabled for headless apps.
+///
+/// The [`crate::render::FlipViewPlugin`] is not part of this group as only some platforms need it.
+pub struct XrPlugins;
+
+impl PluginGroup for XrPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(XrPlugin)
+            .add(XrControllerInputPlugin)
+            .add(XrProjectionPlugin)
+            .add(XrCameraSettingsPlugin)
+            .add(XrGizmoPlugin)
+    }
+}
+
+/// The [`SystemSet`]s of the xr frame, run in order in [`PreUpdate`] after the [`InputSystem`] and in [`Update`].
+///
+/// Platform crates write the poses of their entities in [`XrSet::BackendSync`].
+/// Everything computed from those poses belongs into [`XrSet::Derive`] and systems reacting to the final poses into [`XrSet::Interaction`].
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum XrSet {
+    /// Poses and states are written by the xr platform specific crate.
+    BackendSync,
+    /// Poses and states are derived from the poses of the backend, e.g. a substituted palm.
+    Derive,
+    /// Interaction with the final poses, e.g. pointers or locomotion.
+    Interaction,
+}
+
+/// The core [`Plugin`] of this crate.
+///
+/// Registers the types of all components, adds the [`XrSessionState`] and [`XrMode`] states and configures the [`XrSet`]s.
+pub struct XrPlugin;
+
+impl Plugin for XrPlugin {
+    fn build(&self, app: &mut App) {
+        app.configure_sets(
+            PreUpdate,
+            (XrSet::BackendSync, XrSet::Derive, XrSet::Interaction)
+                .chain()
+                .after(InputSystem),
+        )
+        .configure_sets(
+            Update,
+            (XrSet::BackendSync, XrSet::Derive, XrSet::Interaction).chain(),
+        )
+        .add_systems(
+            PreUpdate,
+            (
+                apply_neck_models,
+                substitute_local_palm::<LeftHanded>,
+                substitute_local_palm::<RightHanded>,
+                (send_tracking_changed, update_active),
+            )
+                .chain()
+                .in_set(XrSet::Derive),
+        )
+        .add_systems(
+            PreUpdate,
+            xr_session_event_system
+                .after(XrSet::BackendSync)
+                .before(XrSet::Derive),
+        )
+        .add_systems(
+            PostUpdate,
+            (recenter_origins, switch_origins, apply_world_scale)
+                .chain()
+                .before(TransformSystem::TransformPropagate),
+        )
+        .init_resource::<XrSpaceSettings>()
+        .add_event::<XrRecenterRequest>()
+        .add_event::<XrOriginSwitchRequest>()
+        .add_event::<XrReferenceSpaceChanged>()
+        .add_event::<XrTrackingChanged>()
+        .add_event::<XrSessionEvent>()
+        .add_state::<XrSessionState>()
+        .add_state::<XrMode>();
+
+        // lib
+        app.register_type::<XrLocal>()
+            .register_type::<XrActive>()
+            .register_type::<XrTrackingState>()
+            .register_type::<XrTrackingChanged>()
+            .register_type::<XrView>();
+
+        // session
+        app.register_type::<XrMode>()
+            .register_type::<XrSessionState>()
+            .register_type::<XrSessionEvent>();
+
+        // space and tracked
+        app.register_type::<XrOrigin>()
+            .register_type::<XrWorldScale>()
+            .register_type::<XrSpaceSettings>()
+            .register_type::<XrRecenterRequest>()
+            .register_type::<XrOriginSwitchRequest>()
+            .register_type::<XrReferenceSpaceChanged>()
+            .register_type::<XrTrackedObject>()
+            .register_type::<XrController>();
+
+        // head, window and pointer
+        app.register_type::<XrHead>()
+            .register_type::<XrHeadset>()
+            .register_type::<XrNeckModel>()
+            .register_type::<XrEye>()
+            .register_type::<XrWindow>()
+            .register_type::<XrPointer>();
+
+        // handedness
+        app.register_type::<Handedness>()
+            .register_type::<LeftHanded>()
+            .register_type::<RightHanded>();
+
+        // hands
+        app.register_type::<Hand>()
+            .register_type::<HandJointRadius>()
+            .register_type::<Forearm>()
+            .register_type::<Wrist>()
+            .register_type::<Palm>()
+            .register_type::<Finger>()
+            .register_type::<Thumb>()
+            .register_type::<Index>()
+            .register_type::<Middle>()
+            .register_type::<Ring>()
+            .register_type::<Little>()
+            .register_type::<FingerJoint>()
+            .register_type::<Metacarpal>()
+            .register_type::<ProximalPhalanx>()
+            .register_type::<IntermediatePhalanx>()
+            .register_type::<DistalPhalanx>()
+            .register_type::<Tip>();
+    }
+}
+
+/// Draws debug [`Gizmos`] for hand joints, controllers, boundaries and planes.
+pub struct XrGizmoPlugin;
+
+impl Plugin for XrGizmoPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            (
+                draw_hand_gizmos,
+                draw_controller_gizmos,
+                draw_boundary_gizmos,
+                draw_plane_gizmos,
+            )
+                .after(TransformSystem::TransformPropagate),
+        );
+    }
+}
@@ -15,7 +15,13 @@ pub use crate::handedness::RightHanded;
 /// As the [`Component`] is an enum it is easy to iterate over the pointers on interaction. Interaction could be triggered by various actions such as a button or the pinch guesture.
 ///
 /// This component should be spawned with another xr component or parented to another xr component including a [`XrActive`].
-#[derive(Component)]
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub enum XrPointer {
     Head,
     Eye,
@@ -25,6 +31,13 @@ pub enum XrPointer {
     Other(usize),
 }
 
+/// Reflection inserts a head pointer and then applies the reflected pointer.
+impl FromWorld for XrPointer {
+    fn from_world(_world: &mut World) -> Self {
+        Self::Head
+    }
+}
+
 /// Still need to figure out how to implement the interaction. Either as an event or as an input.
 #[cfg(notes)]
 mod notes {
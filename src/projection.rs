@@ -0,0 +1,295 @@
+//! The projection of the [`XrView`] cameras.
+//!
+//! Headsets report four asymmetric angles of the field of view for every view, so the symmetric [`PerspectiveProjection`] of bevy does not fit.
+//! The [`XrFovProjection`] builds the projection of a [`crate::head::XrEye`] camera from these angles.
+//! The xr platform specific crate writes the [`XrViewConfig`] of every view and the [`XrProjectionPlugin`] applies its field of view to the projection of the camera.
+
+use bevy::{
+    core_pipeline::{
+        core_3d::{self, Camera3d},
+        tonemapping::{DebandDither, Tonemapping},
+    },
+    math::Vec3A,
+    prelude::*,
+    render::{
+        camera::{
+            camera_system, CameraProjection, CameraProjectionPlugin, CameraRenderGraph,
+            CameraUpdateSystem,
+        },
+        primitives::Frustum,
+        view::{update_frusta, ColorGrading, VisibilitySystems, VisibleEntities},
+        RenderPlugin,
+    },
+    transform::TransformSystem,
+};
+
+use crate::XrView;
+
+/// Adds the [`XrFovProjection`] and applies the [`XrViewConfig`]s to the projections of the views.
+///
+/// The projection is only computed if the [`RenderPlugin`] was added before, so headless apps can keep this plugin.
+pub struct XrProjectionPlugin;
+
+impl Plugin for XrProjectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, apply_view_configs.before(CameraUpdateSystem));
+
+        if app.is_plugin_added::<RenderPlugin>() {
+            app.add_plugins(CameraProjectionPlugin::<XrFovProjection>::default())
+                .add_systems(
+                    PostUpdate,
+                    update_frusta::<XrFovProjection>
+                        .in_set(VisibilitySystems::UpdateProjectionFrusta)
+                        .after(camera_system::<XrFovProjection>)
+                        .after(TransformSystem::TransformPropagate)
+                        .ambiguous_with(update_frusta::<Projection>),
+                );
+        }
+
+        app.register_type::<XrFov>()
+            .register_type::<XrFovProjection>()
+            .register_type::<XrViewConfig>();
+    }
+}
+
+/// The field of view of a view as the angles of its left, right, upper and lower edge to the forward direction in radians.
+///
+/// As in openxr the angles to the left and down are usually negative.
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrFov {
+    pub left: f32,
+    pub right: f32,
+    pub up: f32,
+    pub down: f32,
+}
+
+impl XrFov {
+    /// A field of view centered on the forward direction with the full horizontal and vertical angles.
+    pub fn symmetric(horizontal: f32, vertical: f32) -> Self {
+        Self {
+            left: -horizontal / 2.0,
+            right: horizontal / 2.0,
+            up: vertical / 2.0,
+            down: -vertical / 2.0,
+        }
+    }
+
+    /// A symmetric field of view with the full vertical angle and the aspect ratio of width to height.
+    pub fn from_vertical(vertical: f32, aspect_ratio: f32) -> Self {
+        let horizontal = 2.0 * ((vertical / 2.0).tan() * aspect_ratio).atan();
+        Self::symmetric(horizontal, vertical)
+    }
+
+    /// The full horizontal angle.
+    pub fn horizontal(&self) -> f32 {
+        self.right - self.left
+    }
+
+    /// The full vertical angle.
+    pub fn vertical(&self) -> f32 {
+        self.up - self.down
+    }
+
+    /// The aspect ratio of width to height of the image.
+    pub fn aspect_ratio(&self) -> f32 {
+        (self.right.tan() - self.left.tan()) / (self.up.tan() - self.down.tan())
+    }
+}
+
+impl Default for XrFov {
+    fn default() -> Self {
+        Self::symmetric(90f32.to_radians(), 90f32.to_radians())
+    }
+}
+
+/// A [`CameraProjection`] with an asymmetric field of view, see [`XrFov`].
+///
+/// Like the [`PerspectiveProjection`] the projection is an infinite reverse z projection, the `far` plane is only used for culling and shadows.
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrFovProjection {
+    pub fov: XrFov,
+    /// The distance of the near plane in meters.
+    pub near: f32,
+    /// The distance of the far plane in meters.
+    pub far: f32,
+}
+
+impl Default for XrFovProjection {
+    fn default() -> Self {
+        Self {
+            fov: XrFov::default(),
+            near: 0.1,
+            far: 1000.0,
+        }
+    }
+}
+
+impl CameraProjection for XrFovProjection {
+    fn get_projection_matrix(&self) -> Mat4 {
+        let left = self.fov.left.tan();
+        let right = self.fov.right.tan();
+        let up = self.fov.up.tan();
+        let down = self.fov.down.tan();
+        let width = right - left;
+        let height = up - down;
+        Mat4::from_cols(
+            Vec4::new(2.0 / width, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0 / height, 0.0, 0.0),
+            Vec4::new((right + left) / width, (up + down) / height, 0.0, -1.0),
+            Vec4::new(0.0, 0.0, self.near, 0.0),
+        )
+    }
+
+    /// The aspect ratio is defined by the field of view, the size of the viewport is ignored.
+    fn update(&mut self, _width: f32, _height: f32) {}
+
+    fn far(&self) -> f32 {
+        self.far
+    }
+
+    fn get_frustum_corners(&self, z_near: f32, z_far: f32) -> [Vec3A; 8] {
+        let left = self.fov.left.tan();
+        let right = self.fov.right.tan();
+        let up = self.fov.up.tan();
+        let down = self.fov.down.tan();
+        let near = z_near.abs();
+        let far = z_far.abs();
+        // The same order as the corners of the PerspectiveProjection.
+        [
+            Vec3A::new(near * right, near * down, z_near),
+            Vec3A::new(near * right, near * up, z_near),
+            Vec3A::new(near * left, near * up, z_near),
+            Vec3A::new(near * left, near * down, z_near),
+            Vec3A::new(far * right, far * down, z_far),
+            Vec3A::new(far * right, far * up, z_far),
+            Vec3A::new(far * left, far * up, z_far),
+            Vec3A::new(far * left, far * down, z_far),
+        ]
+    }
+}
+
+/// The configuration of a [`XrView`] reported by the runtime.
+///
+/// This component should be spawned with every [`XrView`] and is updated by the xr platform specific crate.
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrViewConfig {
+    pub fov: XrFov,
+    /// The resolution in pixels the runtime recommends for the image of the view.
+    pub recommended_resolution: UVec2,
+}
+
+/// The components of a [`Camera3dBundle`] with a [`XrFovProjection`] instead of a [`Projection`].
+#[derive(Bundle)]
+pub struct XrFovCameraBundle {
+    pub camera: Camera,
+    pub camera_render_graph: CameraRenderGraph,
+    pub projection: XrFovProjection,
+    pub visible_entities: VisibleEntities,
+    pub frustum: Frustum,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub camera_3d: Camera3d,
+    pub tonemapping: Tonemapping,
+    pub dither: DebandDither,
+    pub color_grading: ColorGrading,
+}
+
+impl Default for XrFovCameraBundle {
+    fn default() -> Self {
+        Self {
+            camera: Default::default(),
+            camera_render_graph: CameraRenderGraph::new(core_3d::graph::NAME),
+            projection: Default::default(),
+            visible_entities: Default::default(),
+            frustum: Default::default(),
+            transform: Default::default(),
+            global_transform: Default::default(),
+            camera_3d: Default::default(),
+            tonemapping: Default::default(),
+            dither: DebandDither::Enabled,
+            color_grading: Default::default(),
+        }
+    }
+}
+
+/// Applies the field of view of the changed [`XrViewConfig`]s to the [`XrFovProjection`] or the [`PerspectiveProjection`] of the view.
+///
+/// A [`PerspectiveProjection`], e.g. of a [`crate::window::XrWindow`], is symmetric, so only the vertical angle is applied and the aspect ratio follows the viewport.
+#[allow(clippy::type_complexity)]
+pub fn apply_view_configs(
+    mut views: Query<
+        (
+            &XrViewConfig,
+            Option<&mut XrFovProjection>,
+            Option<&mut Projection>,
+        ),
+        (With<XrView>, Changed<XrViewConfig>),
+    >,
+) {
+    for (config, fov_projection, projection) in views.iter_mut() {
+        if let Some(mut fov_projection) = fov_projection {
+            if fov_projection.fov != config.fov {
+                fov_projection.fov = config.fov;
+            }
+        }
+        if let Some(mut projection) = projection {
+            if let Projection::Perspective(perspective) = projection.as_mut() {
+                perspective.fov = config.fov.vertical();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ndc(projection: &XrFovProjection, point: Vec3) -> Vec3 {
+        let clip = projection.get_projection_matrix() * point.extend(1.0);
+        clip.truncate() / clip.w
+    }
+
+    #[test]
+    fn fov_projection_maps_edges_to_ndc() {
+        let projection = XrFovProjection {
+            fov: XrFov {
+                left: -0.8,
+                right: 0.6,
+                up: 0.7,
+                down: -0.5,
+            },
+            near: 0.1,
+            far: 100.0,
+        };
+        let fov = projection.fov;
+        for depth in [0.1, 1.0, 25.0] {
+            let at_depth = |x: f32, y: f32| Vec3::new(x.tan() * depth, y.tan() * depth, -depth);
+            assert!((ndc(&projection, at_depth(fov.left, 0.0)).x + 1.0).abs() < 1e-5);
+            assert!((ndc(&projection, at_depth(fov.right, 0.0)).x - 1.0).abs() < 1e-5);
+            assert!((ndc(&projection, at_depth(0.0, fov.up)).y - 1.0).abs() < 1e-5);
+            assert!((ndc(&projection, at_depth(0.0, fov.down)).y + 1.0).abs() < 1e-5);
+        }
+
+        // An infinite reverse z projection, the near plane maps to 1 and the depth approaches 0.
+        assert!((ndc(&projection, Vec3::new(0.0, 0.0, -0.1)).z - 1.0).abs() < 1e-6);
+        assert!(ndc(&projection, Vec3::new(0.0, 0.0, -1.0e6)).z.abs() < 1e-6);
+    }
+}
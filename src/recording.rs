@@ -0,0 +1,438 @@
+//! Recording and playback of the poses and the controller input of a xr session.
+//!
+//! The [`XrRecorder`] captures the [`Transform`] and [`XrTrackingState`] of every named [`XrLocal`] entity and the [`XrControllerEvent`]s of each frame.
+//! An [`XrRecording`] can be saved as a RON file and be replayed by inserting an [`XrPlayback`], which applies the poses to the entities with the same [`Name`] and resends the events.
+//!
+//! The poses are captured after [`XrSet::BackendSync`] and replayed at the same point, so the recording replaces the backend while all derived poses are computed again.
+//! The rig itself is not spawned by the playback, use the [`crate::simulator::XrSimulatorPlugin`] or the backend that recorded the session.
+//!
+//! Requires the `recording` feature.
+
+use std::{fmt, fs, io, path::Path};
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{controller_input::XrControllerEvent, XrLocal, XrSet, XrTrackingState};
+
+pub struct XrRecordingPlugin;
+
+impl Plugin for XrRecordingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrRecorder>().add_systems(
+            PreUpdate,
+            (apply_playback, record_frame)
+                .chain()
+                .after(XrSet::BackendSync)
+                .before(XrSet::Derive),
+        );
+    }
+}
+
+/// The pose of a single entity in a [`XrRecordedFrame`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct XrRecordedPose {
+    /// The [`Name`] of the entity.
+    pub name: String,
+    /// The local transform of the entity.
+    pub transform: Transform,
+    /// The tracking state of the entity.
+    pub tracking_state: XrTrackingState,
+}
+
+/// A single frame of a [`XrRecording`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct XrRecordedFrame {
+    /// The time since the start of the recording in seconds.
+    pub time: f32,
+    /// The poses of all recorded entities.
+    pub poses: Vec<XrRecordedPose>,
+    /// The controller events sent during the frame in the order they were sent.
+    pub controller_events: Vec<XrControllerEvent>,
+}
+
+/// A recorded xr session.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct XrRecording {
+    pub frames: Vec<XrRecordedFrame>,
+}
+
+impl XrRecording {
+    /// The duration of the recording in seconds.
+    pub fn duration(&self) -> f32 {
+        self.frames.last().map(|frame| frame.time).unwrap_or(0.0)
+    }
+
+    /// Serializes the recording into a RON string.
+    pub fn to_ron(&self) -> Result<String, XrRecordingError> {
+        Ok(ron::ser::to_string(self)?)
+    }
+
+    /// Deserializes a recording from a RON string.
+    pub fn from_ron(ron: &str) -> Result<Self, XrRecordingError> {
+        Ok(ron::de::from_str(ron)?)
+    }
+
+    /// Saves the recording as a RON file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), XrRecordingError> {
+        fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+
+    /// Loads a recording from a RON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, XrRecordingError> {
+        Self::from_ron(&fs::read_to_string(path)?)
+    }
+}
+
+/// An error while saving or loading a [`XrRecording`].
+#[derive(Debug)]
+pub enum XrRecordingError {
+    /// The file could not be read or written.
+    Io(io::Error),
+    /// The recording could not be serialized.
+    Serialize(ron::Error),
+    /// The recording could not be deserialized.
+    Deserialize(ron::error::SpannedError),
+}
+
+impl fmt::Display for XrRecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XrRecordingError::Io(error) => write!(f, "xr recording io error: {error}"),
+            XrRecordingError::Serialize(error) => {
+                write!(f, "xr recording serialization error: {error}")
+            }
+            XrRecordingError::Deserialize(error) => {
+                write!(f, "xr recording deserialization error: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for XrRecordingError {}
+
+impl From<io::Error> for XrRecordingError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<ron::Error> for XrRecordingError {
+    fn from(value: ron::Error) -> Self {
+        Self::Serialize(value)
+    }
+}
+
+impl From<ron::error::SpannedError> for XrRecordingError {
+    fn from(value: ron::error::SpannedError) -> Self {
+        Self::Deserialize(value)
+    }
+}
+
+/// This [`Resource`] records the xr session while it is started.
+#[derive(Resource, Debug, Default)]
+pub struct XrRecorder {
+    recording: Option<XrRecording>,
+    start: f32,
+}
+
+impl XrRecorder {
+    /// Starts a new recording and discards a running one.
+    pub fn start(&mut self) {
+        self.recording = Some(XrRecording::default());
+        self.start = f32::NAN;
+    }
+
+    /// Stops the recording and returns it.
+    pub fn stop(&mut self) -> Option<XrRecording> {
+        self.recording.take()
+    }
+
+    /// Is the recorder recording?
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+}
+
+/// Records the poses and controller events of the frame into the [`XrRecorder`].
+pub fn record_frame(
+    time: Res<Time>,
+    mut recorder: ResMut<XrRecorder>,
+    mut controller_events: EventReader<XrControllerEvent>,
+    poses: Query<(&Name, &Transform, &XrTrackingState), With<XrLocal>>,
+) {
+    if !recorder.is_recording() {
+        controller_events.clear();
+        return;
+    }
+
+    if recorder.start.is_nan() {
+        recorder.start = time.elapsed_seconds();
+    }
+    let frame = XrRecordedFrame {
+        time: time.elapsed_seconds() - recorder.start,
+        poses: poses
+            .iter()
+            .map(|(name, transform, tracking_state)| XrRecordedPose {
+                name: name.to_string(),
+                transform: *transform,
+                tracking_state: *tracking_state,
+            })
+            .collect(),
+        controller_events: controller_events.read().cloned().collect(),
+    };
+
+    if let Some(recording) = recorder.recording.as_mut() {
+        recording.frames.push(frame);
+    }
+}
+
+/// This [`Resource`] replays a [`XrRecording`] while it exists.
+///
+/// By default one recorded frame is replayed per app update, which keeps the playback deterministic.
+/// With [`XrPlayback::realtime`] the frames are replayed by their recorded time instead.
+#[derive(Resource, Debug, Clone)]
+pub struct XrPlayback {
+    pub recording: XrRecording,
+    /// Replays the frames by their recorded time instead of one frame per update.
+    pub realtime: bool,
+    /// Restarts the playback at the end of the recording.
+    pub looping: bool,
+    frame: usize,
+    start: f32,
+}
+
+impl XrPlayback {
+    pub fn new(recording: XrRecording) -> Self {
+        Self {
+            recording,
+            realtime: false,
+            looping: false,
+            frame: 0,
+            start: f32::NAN,
+        }
+    }
+
+    /// The index of the next frame to be replayed.
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Is the playback at the end of the recording?
+    pub fn finished(&self) -> bool {
+        self.frame >= self.recording.frames.len()
+    }
+
+    /// Restarts the playback at the first frame.
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.start = f32::NAN;
+    }
+}
+
+/// Applies the poses and resends the controller events of the [`XrPlayback`].
+pub fn apply_playback(
+    time: Res<Time>,
+    playback: Option<ResMut<XrPlayback>>,
+    mut controller_events: EventWriter<XrControllerEvent>,
+    mut poses: Query<(&Name, &mut Transform, &mut XrTrackingState), With<XrLocal>>,
+) {
+    let Some(mut playback) = playback else {
+        return;
+    };
+    if playback.finished() && playback.looping {
+        playback.restart();
+    }
+    if playback.start.is_nan() {
+        playback.start = time.elapsed_seconds();
+    }
+
+    // Send the events of every frame due this update and apply the poses of the latest one.
+    let end = if playback.realtime {
+        let elapsed = time.elapsed_seconds() - playback.start;
+        playback.frame
+            + playback.recording.frames[playback.frame..]
+                .iter()
+                .take_while(|frame| frame.time <= elapsed)
+                .count()
+    } else {
+        (playback.frame + 1).min(playback.recording.frames.len())
+    };
+    if end == playback.frame {
+        return;
+    }
+    let start = playback.frame;
+    playback.frame = end;
+
+    let frames = &playback.recording.frames[start..end];
+    for frame in frames {
+        controller_events.send_batch(frame.controller_events.iter().cloned());
+    }
+
+    let latest: HashMap<&str, &XrRecordedPose> = frames
+        .last()
+        .map(|frame| {
+            frame
+                .poses
+                .iter()
+                .map(|pose| (pose.name.as_str(), pose))
+                .collect()
+        })
+        .unwrap_or_default();
+    for (name, mut transform, mut tracking_state) in poses.iter_mut() {
+        if let Some(pose) = latest.get(name.as_str()) {
+            *transform = pose.transform;
+            tracking_state.set_if_neq(pose.tracking_state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::event::ManualEventReader, input::InputPlugin};
+
+    use super::*;
+    use crate::{
+        controller::XrController,
+        controller_input::{
+            XrControllerInputPlugin, XrControllerInputType, XrControllerPressChangedEvent,
+        },
+        plugin::XrPlugin,
+    };
+
+    fn press(value: f32) -> XrControllerEvent {
+        XrControllerPressChangedEvent::new(
+            XrController::Left,
+            XrControllerInputType::Trigger,
+            value,
+        )
+        .into()
+    }
+
+    fn recording() -> XrRecording {
+        XrRecording {
+            frames: vec![
+                XrRecordedFrame {
+                    time: 0.0,
+                    poses: vec![XrRecordedPose {
+                        name: "XrHeadset".to_string(),
+                        transform: Transform::from_xyz(0.0, 1.6, 0.0),
+                        tracking_state: XrTrackingState::Tracked,
+                    }],
+                    controller_events: vec![press(1.0)],
+                },
+                XrRecordedFrame {
+                    time: 0.5,
+                    poses: vec![XrRecordedPose {
+                        name: "XrHeadset".to_string(),
+                        transform: Transform::from_xyz(0.2, 1.6, -0.1)
+                            .with_rotation(Quat::from_rotation_y(0.5)),
+                        tracking_state: XrTrackingState::Lost,
+                    }],
+                    controller_events: vec![press(0.0)],
+                },
+            ],
+        }
+    }
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            InputPlugin,
+            XrPlugin,
+            XrControllerInputPlugin,
+            XrRecordingPlugin,
+        ));
+        app
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let recording = recording();
+        let ron = recording.to_ron().unwrap();
+        assert_eq!(XrRecording::from_ron(&ron).unwrap(), recording);
+
+        let path = std::env::temp_dir().join(format!("xr_recording_{}.ron", std::process::id()));
+        recording.save(&path).unwrap();
+        let loaded = XrRecording::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), recording);
+
+        assert!(matches!(
+            XrRecording::from_ron("(frames: [(time: 0.0)])"),
+            Err(XrRecordingError::Deserialize(_))
+        ));
+    }
+
+    #[test]
+    fn playback_replays_poses_and_events() {
+        let mut app = app();
+        let headset = app
+            .world
+            .spawn((
+                Name::new("XrHeadset"),
+                Transform::default(),
+                XrTrackingState::Tracked,
+                XrLocal,
+            ))
+            .id();
+        let recording = recording();
+        app.insert_resource(XrPlayback::new(recording.clone()));
+
+        let mut reader = ManualEventReader::<XrControllerEvent>::default();
+        for frame in recording.frames.iter() {
+            app.update();
+            assert_eq!(
+                app.world.get::<Transform>(headset),
+                Some(&frame.poses[0].transform)
+            );
+            assert_eq!(
+                app.world.get::<XrTrackingState>(headset),
+                Some(&frame.poses[0].tracking_state)
+            );
+            let events = app.world.resource::<Events<XrControllerEvent>>();
+            let sent: Vec<_> = reader.read(events).cloned().collect();
+            assert_eq!(sent, frame.controller_events);
+        }
+        assert!(app.world.resource::<XrPlayback>().finished());
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let mut app = app();
+        let headset = app
+            .world
+            .spawn((
+                Name::new("XrHeadset"),
+                Transform::default(),
+                XrTrackingState::Tracked,
+                XrLocal,
+            ))
+            .id();
+
+        app.world.resource_mut::<XrRecorder>().start();
+        let poses: Vec<Transform> = (0..3)
+            .map(|i| Transform::from_xyz(i as f32, 1.6, 0.0))
+            .collect();
+        for (i, pose) in poses.iter().enumerate() {
+            *app.world.get_mut::<Transform>(headset).unwrap() = *pose;
+            app.world.send_event(press(i as f32 / 2.0));
+            app.update();
+        }
+        let recording = app.world.resource_mut::<XrRecorder>().stop().unwrap();
+        assert_eq!(recording.frames.len(), poses.len());
+        for (frame, (i, pose)) in recording.frames.iter().zip(poses.iter().enumerate()) {
+            assert_eq!(frame.poses[0].transform, *pose);
+            assert_eq!(frame.controller_events, vec![press(i as f32 / 2.0)]);
+        }
+
+        *app.world.get_mut::<Transform>(headset).unwrap() = Transform::default();
+        app.insert_resource(XrPlayback::new(recording));
+        for pose in poses.iter() {
+            app.update();
+            assert_eq!(app.world.get::<Transform>(headset), Some(pose));
+        }
+    }
+}
@@ -0,0 +1,550 @@
+//! Replication of xr rigs to remote players.
+//!
+//! The [`XrReplicationPlugin`] captures a [`XrSnapshot`] of the local rig at a fixed rate and sends it as a [`XrLocalSnapshot`] event.
+//! The networking layer of the app transports the snapshots and sends the received ones as [`XrRemoteSnapshot`] events.
+//! For every remote player an origin entity with a [`XrRemotePlayer`] is spawned and the replicated parts are spawned as its children without the [`XrLocal`] component.
+//! The replicas are interpolated between the buffered snapshots with a delay to hide the jitter of the transport.
+//! A remote player is despawned with its replicas when the networking layer sends a [`XrRemotePlayerLeft`] event or no snapshot arrived within the [`XrReplicationSettings::timeout`].
+//!
+//! With the `serialize` feature the snapshots implement serde and can be encoded in any format.
+//! Inserting a [`XrLoopback`] resource sends the local snapshots back as a remote player for testing without a network.
+
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, transform::TransformSystem, utils::HashMap};
+
+use crate::{
+    controller::XrController,
+    handedness::{Handedness, LeftHanded, RightHanded},
+    hands::{Hand, HandJointRadius},
+    head::{XrHead, XrHeadset},
+    space::XrOrigin,
+    XrActive, XrLocal, XrSet, XrTrackingState,
+};
+
+pub struct XrReplicationPlugin;
+
+impl Plugin for XrReplicationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrReplicationSettings>()
+            .add_event::<XrLocalSnapshot>()
+            .add_event::<XrRemoteSnapshot>()
+            .add_event::<XrRemotePlayerLeft>()
+            .add_systems(
+                PreUpdate,
+                (
+                    loopback_snapshots.run_if(resource_exists::<XrLoopback>()),
+                    receive_remote_snapshots,
+                    despawn_remote_players,
+                    interpolate_remote_players,
+                )
+                    .chain()
+                    .in_set(XrSet::BackendSync),
+            )
+            .add_systems(
+                PostUpdate,
+                capture_local_snapshot.after(TransformSystem::TransformPropagate),
+            );
+
+        app.register_type::<XrRemotePlayer>()
+            .register_type::<XrSnapshotBuffer>()
+            .register_type::<XrSnapshotPart>()
+            .register_type::<XrReplicationSettings>();
+    }
+}
+
+/// Settings of the [`XrReplicationPlugin`].
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Debug, Default)]
+pub struct XrReplicationSettings {
+    /// The time between two captured local snapshots in seconds.
+    pub send_interval: f32,
+    /// The delay of the remote replicas behind the newest received snapshot in seconds.
+    ///
+    /// Should be larger than the send interval plus the expected jitter of the transport.
+    pub interpolation_delay: f32,
+    /// The maximum amount of buffered snapshots per remote player.
+    pub buffer_size: usize,
+    /// The time in seconds without a received snapshot after which a remote player is despawned.
+    pub timeout: f32,
+}
+
+impl Default for XrReplicationSettings {
+    fn default() -> Self {
+        Self {
+            send_interval: 1.0 / 30.0,
+            interpolation_delay: 0.1,
+            buffer_size: 32,
+            timeout: 5.0,
+        }
+    }
+}
+
+/// The replicated part of a rig. Remote replicas carry this [`Component`].
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrSnapshotPart {
+    Headset,
+    Head,
+    Controller(XrController),
+    HandJoint(Handedness, Hand),
+}
+
+/// Reflection inserts the headset part and then applies the reflected part of the replica.
+impl FromWorld for XrSnapshotPart {
+    fn from_world(_world: &mut World) -> Self {
+        Self::Headset
+    }
+}
+
+/// The pose of a single part in a [`XrSnapshot`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct XrSnapshotPose {
+    pub part: XrSnapshotPart,
+    /// The transform relative to the [`XrOrigin`].
+    pub transform: Transform,
+    pub tracking_state: XrTrackingState,
+}
+
+/// A snapshot of the poses of a rig.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct XrSnapshot {
+    /// Increases with every captured snapshot.
+    pub sequence: u32,
+    /// The elapsed time of the sender in seconds.
+    pub time: f64,
+    /// The transform of the [`XrOrigin`].
+    pub origin: Transform,
+    pub poses: Vec<XrSnapshotPose>,
+}
+
+/// A snapshot of the local rig which should be sent to the remote players.
+#[derive(Event, Debug, Clone)]
+pub struct XrLocalSnapshot(pub XrSnapshot);
+
+/// A snapshot received from a remote player.
+#[derive(Event, Debug, Clone)]
+pub struct XrRemoteSnapshot {
+    /// The id of the remote player, defined by the networking layer.
+    pub player: u64,
+    pub snapshot: XrSnapshot,
+}
+
+/// Sent by the networking layer when a remote player left, despawns the player and its replicas.
+#[derive(Event, Debug, Clone)]
+pub struct XrRemotePlayerLeft {
+    /// The id of the remote player, defined by the networking layer.
+    pub player: u64,
+}
+
+/// The defining [`Component`] for the origin of a remote player.
+///
+/// The replicated parts are spawned as children of this entity.
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrRemotePlayer(pub u64);
+
+/// The jitter buffer of a remote player.
+#[derive(Component, Debug, Default, Reflect)]
+#[reflect(Component, Debug, Default)]
+pub struct XrSnapshotBuffer {
+    #[reflect(ignore)]
+    snapshots: VecDeque<XrSnapshot>,
+    render_time: Option<f64>,
+    /// The local elapsed time of the last received snapshot in seconds.
+    received_time: f64,
+}
+
+impl XrSnapshotBuffer {
+    /// Inserts a snapshot ordered by time. Outdated and duplicated snapshots are dropped.
+    pub fn push(&mut self, snapshot: XrSnapshot, buffer_size: usize) {
+        if self
+            .render_time
+            .is_some_and(|render_time| snapshot.time < render_time)
+        {
+            return;
+        }
+        let index = self
+            .snapshots
+            .partition_point(|buffered| buffered.time < snapshot.time);
+        if self
+            .snapshots
+            .get(index)
+            .is_some_and(|buffered| buffered.sequence == snapshot.sequence)
+        {
+            return;
+        }
+        self.snapshots.insert(index, snapshot);
+        while self.snapshots.len() > buffer_size.max(2) {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// The amount of buffered snapshots.
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// Is the buffer empty?
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Advances the render time by `delta` towards the newest snapshot minus the `delay` and drops the snapshots behind it.
+    fn advance(&mut self, delta: f64, delay: f64) {
+        let Some(newest) = self.snapshots.back() else {
+            return;
+        };
+        let target = newest.time - delay;
+        let render_time = match self.render_time {
+            Some(render_time) => {
+                let render_time = render_time + delta;
+                // Snap on large drift, e.g. after a stall, and correct small drift smoothly.
+                if (target - render_time).abs() > 4.0 * delay {
+                    target
+                } else {
+                    render_time + (target - render_time) * 0.1
+                }
+            }
+            None => target,
+        };
+        let render_time = render_time.min(newest.time);
+        self.render_time = Some(render_time);
+
+        while self.snapshots.len() > 1 && self.snapshots[1].time <= render_time {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Returns the snapshots around the render time and the interpolation factor between them.
+    fn sample(&self) -> Option<(&XrSnapshot, &XrSnapshot, f32)> {
+        let render_time = self.render_time?;
+        let from = self.snapshots.front()?;
+        let to = self.snapshots.get(1).unwrap_or(from);
+        let span = to.time - from.time;
+        let t = if span > 0.0 {
+            ((render_time - from.time) / span).clamp(0.0, 1.0) as f32
+        } else {
+            1.0
+        };
+        Some((from, to, t))
+    }
+}
+
+/// Sends the local snapshots back as the remote player [`XrLoopback::player`] after [`XrLoopback::latency`] seconds.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct XrLoopback {
+    pub player: u64,
+    pub latency: f32,
+    queue: VecDeque<(f64, XrSnapshot)>,
+}
+
+impl XrLoopback {
+    pub fn new(player: u64, latency: f32) -> Self {
+        Self {
+            player,
+            latency,
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+fn snapshot_part(
+    headset: bool,
+    head: bool,
+    xr_controller: Option<&XrController>,
+    hand: Option<(&Hand, &Handedness)>,
+) -> Option<XrSnapshotPart> {
+    if headset {
+        Some(XrSnapshotPart::Headset)
+    } else if head {
+        Some(XrSnapshotPart::Head)
+    } else if let Some(xr_controller) = xr_controller {
+        Some(XrSnapshotPart::Controller(*xr_controller))
+    } else {
+        hand.map(|(hand, handedness)| XrSnapshotPart::HandJoint(*handedness, *hand))
+    }
+}
+
+/// Captures a [`XrSnapshot`] of the local rig every [`XrReplicationSettings::send_interval`] and sends it as a [`XrLocalSnapshot`].
+#[allow(clippy::type_complexity)]
+pub fn capture_local_snapshot(
+    time: Res<Time>,
+    settings: Res<XrReplicationSettings>,
+    mut sequence: Local<u32>,
+    mut last_capture: Local<Option<f64>>,
+    origin: Query<&GlobalTransform, (With<XrLocal>, With<XrOrigin>)>,
+    parts: Query<
+        (
+            &GlobalTransform,
+            &XrTrackingState,
+            Has<XrHeadset>,
+            Has<XrHead>,
+            Option<&XrController>,
+            Option<(&Hand, &Handedness)>,
+        ),
+        (With<XrLocal>, Without<XrOrigin>),
+    >,
+    mut snapshots: EventWriter<XrLocalSnapshot>,
+) {
+    let now = time.elapsed_seconds_f64();
+    if last_capture.is_some_and(|last| now - last < settings.send_interval as f64) {
+        return;
+    }
+    let Ok(origin) = origin.get_single() else {
+        return;
+    };
+    *last_capture = Some(now);
+    *sequence = sequence.wrapping_add(1);
+
+    let poses = parts
+        .iter()
+        .filter_map(
+            |(transform, tracking_state, headset, head, xr_controller, hand)| {
+                Some(XrSnapshotPose {
+                    part: snapshot_part(headset, head, xr_controller, hand)?,
+                    // Nested parts like the finger joints are relative to their parent, the replicas are children of the origin.
+                    transform: transform.reparented_to(origin),
+                    tracking_state: *tracking_state,
+                })
+            },
+        )
+        .collect();
+
+    snapshots.send(XrLocalSnapshot(XrSnapshot {
+        sequence: *sequence,
+        time: now,
+        origin: origin.compute_transform(),
+        poses,
+    }));
+}
+
+/// Pushes the [`XrRemoteSnapshot`]s into the buffers of the remote players and spawns the origins of new players.
+pub fn receive_remote_snapshots(
+    time: Res<Time>,
+    settings: Res<XrReplicationSettings>,
+    mut remote_snapshots: EventReader<XrRemoteSnapshot>,
+    mut players: Query<(&XrRemotePlayer, &mut XrSnapshotBuffer)>,
+    mut commands: Commands,
+) {
+    let now = time.elapsed_seconds_f64();
+    let mut new_players: HashMap<u64, XrSnapshotBuffer> = HashMap::default();
+
+    for remote_snapshot in remote_snapshots.read() {
+        let snapshot = remote_snapshot.snapshot.clone();
+        match players
+            .iter_mut()
+            .find(|(player, _)| player.0 == remote_snapshot.player)
+        {
+            Some((_, mut buffer)) => {
+                buffer.push(snapshot, settings.buffer_size);
+                buffer.received_time = now;
+            }
+            None => {
+                let buffer = new_players.entry(remote_snapshot.player).or_default();
+                buffer.push(snapshot, settings.buffer_size);
+                buffer.received_time = now;
+            }
+        }
+    }
+
+    for (player, buffer) in new_players {
+        info!("Xr remote player {} joined", player);
+        commands.spawn((
+            Name::new(format!("XrRemotePlayer_{player}")),
+            SpatialBundle::default(),
+            XrOrigin::Other,
+            XrTrackingState::Tracked,
+            XrActive(true),
+            XrRemotePlayer(player),
+            buffer,
+        ));
+    }
+}
+
+/// Despawns the remote players which left or timed out together with their replicas.
+pub fn despawn_remote_players(
+    time: Res<Time>,
+    settings: Res<XrReplicationSettings>,
+    mut left_players: EventReader<XrRemotePlayerLeft>,
+    players: Query<(Entity, &XrRemotePlayer, &XrSnapshotBuffer)>,
+    mut commands: Commands,
+) {
+    let now = time.elapsed_seconds_f64();
+    let left: Vec<u64> = left_players.read().map(|left| left.player).collect();
+
+    for (entity, player, buffer) in players.iter() {
+        if left.contains(&player.0) {
+            info!("Xr remote player {} left", player.0);
+        } else if now - buffer.received_time > settings.timeout as f64 {
+            info!("Xr remote player {} timed out", player.0);
+        } else {
+            continue;
+        }
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Interpolates the replicas of the remote players and spawns replicas for newly replicated parts.
+#[allow(clippy::type_complexity)]
+pub fn interpolate_remote_players(
+    time: Res<Time>,
+    settings: Res<XrReplicationSettings>,
+    mut players: Query<
+        (
+            Entity,
+            &mut XrSnapshotBuffer,
+            &mut Transform,
+            Option<&Children>,
+        ),
+        With<XrRemotePlayer>,
+    >,
+    mut replicas: Query<
+        (&XrSnapshotPart, &mut Transform, &mut XrTrackingState),
+        Without<XrRemotePlayer>,
+    >,
+    mut commands: Commands,
+) {
+    for (player, mut buffer, mut origin, children) in players.iter_mut() {
+        buffer.advance(
+            time.delta_seconds_f64(),
+            settings.interpolation_delay as f64,
+        );
+        let Some((from, to, t)) = buffer.sample() else {
+            continue;
+        };
+
+        *origin = interpolate_transform(&from.origin, &to.origin, t);
+
+        let mut replicated: HashMap<XrSnapshotPart, (Transform, XrTrackingState)> = from
+            .poses
+            .iter()
+            .map(|pose| {
+                let transform = to
+                    .poses
+                    .iter()
+                    .find(|to_pose| to_pose.part == pose.part)
+                    .map(|to_pose| interpolate_transform(&pose.transform, &to_pose.transform, t))
+                    .unwrap_or(pose.transform);
+                (pose.part, (transform, pose.tracking_state))
+            })
+            .collect();
+
+        for child in children.into_iter().flatten() {
+            let Ok((part, mut transform, mut tracking_state)) = replicas.get_mut(*child) else {
+                continue;
+            };
+            match replicated.remove(part) {
+                Some((pose, state)) => {
+                    *transform = pose;
+                    tracking_state.set_if_neq(state);
+                }
+                None => {
+                    tracking_state.set_if_neq(XrTrackingState::NotRequested);
+                }
+            }
+        }
+
+        for (part, (transform, tracking_state)) in replicated {
+            let replica = spawn_replica(&mut commands, part, transform, tracking_state);
+            commands.entity(player).add_child(replica);
+        }
+    }
+}
+
+fn interpolate_transform(from: &Transform, to: &Transform, t: f32) -> Transform {
+    Transform {
+        translation: from.translation.lerp(to.translation, t),
+        rotation: from.rotation.slerp(to.rotation, t),
+        scale: from.scale.lerp(to.scale, t),
+    }
+}
+
+/// Spawns the replica of a replicated part with the components of the corresponding local bundle except [`XrLocal`].
+fn spawn_replica(
+    commands: &mut Commands,
+    part: XrSnapshotPart,
+    transform: Transform,
+    tracking_state: XrTrackingState,
+) -> Entity {
+    let mut replica = commands.spawn((
+        Name::new(format!("XrRemote_{part:?}")),
+        SpatialBundle::from_transform(transform),
+        part,
+        tracking_state,
+        XrActive::from(tracking_state),
+    ));
+    match part {
+        XrSnapshotPart::Headset => {
+            replica.insert(XrHeadset);
+        }
+        XrSnapshotPart::Head => {
+            replica.insert(XrHead);
+        }
+        XrSnapshotPart::Controller(xr_controller) => {
+            replica.insert(xr_controller);
+            match xr_controller {
+                XrController::Left => {
+                    replica.insert((Handedness::Left, LeftHanded));
+                }
+                XrController::Right => {
+                    replica.insert((Handedness::Right, RightHanded));
+                }
+                XrController::Other(_) => {}
+            }
+        }
+        XrSnapshotPart::HandJoint(handedness, hand) => {
+            replica.insert((hand, handedness, HandJointRadius(None)));
+            match handedness {
+                Handedness::Left => {
+                    replica.insert(LeftHanded);
+                }
+                Handedness::Right => {
+                    replica.insert(RightHanded);
+                }
+            }
+        }
+    }
+    replica.id()
+}
+
+/// Sends the [`XrLocalSnapshot`]s back as [`XrRemoteSnapshot`]s of the [`XrLoopback`] player.
+pub fn loopback_snapshots(
+    time: Res<Time>,
+    mut loopback: ResMut<XrLoopback>,
+    mut local_snapshots: EventReader<XrLocalSnapshot>,
+    mut remote_snapshots: EventWriter<XrRemoteSnapshot>,
+) {
+    let now = time.elapsed_seconds_f64();
+    let latency = loopback.latency as f64;
+    for local_snapshot in local_snapshots.read() {
+        loopback
+            .queue
+            .push_back((now + latency, local_snapshot.0.clone()));
+    }
+
+    while loopback
+        .queue
+        .front()
+        .is_some_and(|(deliver_at, _)| *deliver_at <= now)
+    {
+        if let Some((_, snapshot)) = loopback.queue.pop_front() {
+            remote_snapshots.send(XrRemoteSnapshot {
+                player: loopback.player,
+                snapshot,
+            });
+        }
+    }
+}
@@ -32,20 +32,34 @@ use bevy::{
 
 pub struct FlipViewPlugin;
 
-#[derive(Component, Clone, Copy, ExtractComponent, PartialEq, Eq)]
+#[derive(Component, Debug, Clone, Copy, ExtractComponent, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub enum FlipView {
     X,
     Y,
     XY,
 }
 
+/// Reflection inserts a horizontal flip and then applies the reflected one.
+impl FromWorld for FlipView {
+    fn from_world(_world: &mut World) -> Self {
+        Self::X
+    }
+}
+
 const FLIP_VIEW_HANDLE: Handle<Shader> = Handle::weak_from_u128(9837534426033940724);
 
 impl Plugin for FlipViewPlugin {
     fn build(&self, app: &mut App) {
         load_internal_asset!(app, FLIP_VIEW_HANDLE, "flip_view.wgsl", Shader::from_wgsl);
 
-        app.add_plugins((ExtractComponentPlugin::<FlipView>::default(),));
+        app.add_plugins((ExtractComponentPlugin::<FlipView>::default(),))
+            .register_type::<FlipView>();
 
         let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
             return;
@@ -0,0 +1,106 @@
+//! The lifecycle of a xr session as bevy [`States`].
+
+use bevy::prelude::*;
+
+pub use crate::XrMode;
+
+/// This [`States`] defines the lifecycle of the xr session.
+///
+/// The states follow the openxr session lifecycle and are set by the xr platform specific crate through a [`XrSessionEvent::StateChanged`] event.
+/// Use [`OnEnter`] and [`OnExit`] or the [`in_state`] run condition to react to the lifecycle, e.g. pausing gameplay when [`XrSessionState::Focused`] is exited.
+#[derive(States, Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrSessionState {
+    /// No session is running or the session is waiting to become ready.
+    #[default]
+    Idle,
+    /// The session is ready to be started by the platform.
+    Ready,
+    /// The frame loop is synchronized with the runtime but nothing is shown to the user.
+    Synchronized,
+    /// The session is visible to the user but does not receive input.
+    Visible,
+    /// The session is visible and receives input.
+    Focused,
+    /// The session is stopping and should be ended by the platform.
+    Stopping,
+    /// The session is exiting and the application should release its xr resources.
+    Exiting,
+}
+
+impl XrSessionState {
+    /// Is the session running? True for all states in which frames are submitted to the runtime.
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self,
+            XrSessionState::Synchronized | XrSessionState::Visible | XrSessionState::Focused
+        )
+    }
+
+    /// Is the session visible to the user?
+    pub fn is_visible(&self) -> bool {
+        matches!(self, XrSessionState::Visible | XrSessionState::Focused)
+    }
+
+    /// Does the session receive input?
+    pub fn is_focused(&self) -> bool {
+        matches!(self, XrSessionState::Focused)
+    }
+}
+
+/// A xr session event.
+///
+/// This event is sent by the xr platform specific crate whenever the runtime reports a change of the session.
+/// The [`xr_session_event_system`] applies the changes to the [`XrSessionState`] and [`XrMode`] states.
+#[derive(Event, Debug, Copy, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrSessionEvent {
+    /// The session has transitioned to another state.
+    StateChanged(XrSessionState),
+    /// The type of xr experience has changed, e.g. a session was started in AR or has ended.
+    ModeChanged(XrMode),
+}
+
+impl From<XrSessionState> for XrSessionEvent {
+    fn from(value: XrSessionState) -> Self {
+        Self::StateChanged(value)
+    }
+}
+
+impl From<XrMode> for XrSessionEvent {
+    fn from(value: XrMode) -> Self {
+        Self::ModeChanged(value)
+    }
+}
+
+/// Applies [`XrSessionEvent`]s to the [`NextState`] of [`XrSessionState`] and [`XrMode`].
+///
+/// If multiple events of the same kind are sent in one frame the last one wins.
+pub fn xr_session_event_system(
+    mut session_events: EventReader<XrSessionEvent>,
+    mut session_state: ResMut<NextState<XrSessionState>>,
+    mut mode: ResMut<NextState<XrMode>>,
+) {
+    for session_event in session_events.read() {
+        match session_event {
+            XrSessionEvent::StateChanged(state) => {
+                info!("Xr session {:?}", state);
+                session_state.set(*state);
+            }
+            XrSessionEvent::ModeChanged(new_mode) => {
+                info!("Xr mode {:?}", new_mode);
+                mode.set(*new_mode);
+            }
+        }
+    }
+}
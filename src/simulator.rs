@@ -0,0 +1,593 @@
+//! A desktop xr backend which simulates a headset, two controllers and two hands with keyboard and mouse.
+//!
+//! The simulator is a [`XrBackend`] added with the [`XrRuntimePlugin`], so it spawns the same rig and reports poses and events through the same contract as a xr platform specific crate.
+//! It is intended for developing and testing xr apps without a headset, requires the [`crate::XrPlugins`] and replaces the [`XrRuntimePlugin`] of a platform crate.
+//!
+//! ## Controls
+//!
+//! - Hold the right mouse button and move the mouse to look around.
+//! - `W`, `A`, `S`, `D` move the headset, `Q` and `E` move it down and up.
+//! - Input goes to the right controller, hold left `Alt` to use the left controller instead.
+//! - Left mouse button presses the trigger, `G` the grip, `1` the A or X, `2` the B or Y and `M` the option button.
+//! - The arrow keys move the stick.
+
+use std::f32::consts::FRAC_PI_3;
+
+use bevy::{
+    ecs::event::ManualEventReader,
+    input::mouse::MouseMotion,
+    prelude::*,
+    render::camera::Viewport,
+    utils::HashMap,
+    window::{PrimaryWindow, WindowFocused},
+};
+
+use crate::{
+    backend::{
+        spawn_backend_rig, sync_backend, XrBackend, XrBackendFrame, XrCapabilities,
+        XrRigDescriptor, XrRigPart, XrRuntime, XrRuntimePlugin,
+    },
+    controller::XrController,
+    controller_input::{
+        XrControllerAxis, XrControllerAxisChangedEvent, XrControllerAxisType, XrControllerInfo,
+        XrControllerInputType, XrControllerPress, XrControllerPressChangedEvent, XrControllerState,
+        XrControllerStateChangedEvent, XrControllerTouchChangedEvent,
+    },
+    handedness::Handedness,
+    hands::Hand,
+    head::XrEye,
+    projection::{XrFov, XrViewConfig},
+    session::XrSessionState,
+    space::{XrOrigin, XrReferenceSpaceChanged},
+    XrMode, XrSet, XrTrackingState,
+};
+
+pub struct XrSimulatorPlugin;
+
+impl Plugin for XrSimulatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrSimulatorSettings>()
+            .add_plugins(XrRuntimePlugin::new(XrSimulatorBackend::default()))
+            .add_systems(Startup, mark_simulated_rig.after(spawn_backend_rig))
+            .add_systems(
+                PreUpdate,
+                simulate_viewports
+                    .after(sync_backend)
+                    .in_set(XrSet::BackendSync),
+            );
+
+        app.register_type::<XrSimulated>()
+            .register_type::<XrSimulatorSettings>();
+    }
+}
+
+/// Marks the entities of the rig of the [`XrSimulatorBackend`].
+#[derive(Component, Reflect, Default)]
+#[reflect(Component, Default)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrSimulated;
+
+/// Settings of the [`XrSimulatorPlugin`].
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Debug, Default)]
+pub struct XrSimulatorSettings {
+    /// The height of the headset above the [`XrOrigin`] in meters.
+    pub eye_height: f32,
+    /// The distance between the eyes in meters.
+    pub ipd: f32,
+    /// The speed of the headset in meters per second.
+    pub move_speed: f32,
+    /// The rotation of the headset in radians per pixel of mouse motion.
+    pub look_sensitivity: f32,
+    /// Renders both eyes side by side. Otherwise only the left eye is rendered.
+    pub stereo: bool,
+    /// Tracks the joints of both hands. Otherwise the joints are not requested.
+    pub hands: bool,
+}
+
+impl Default for XrSimulatorSettings {
+    fn default() -> Self {
+        Self {
+            eye_height: 1.7,
+            ipd: 0.064,
+            move_speed: 1.5,
+            look_sensitivity: 0.003,
+            stereo: false,
+            hands: true,
+        }
+    }
+}
+
+/// The keyboard and mouse bindings of the simulated presses.
+const PRESS_BINDINGS: [(XrControllerInputType, Option<KeyCode>); 5] = [
+    (XrControllerInputType::Trigger, None),
+    (XrControllerInputType::Grip, Some(KeyCode::G)),
+    (XrControllerInputType::AorX, Some(KeyCode::Key1)),
+    (XrControllerInputType::BorY, Some(KeyCode::Key2)),
+    (XrControllerInputType::Option, Some(KeyCode::M)),
+];
+
+/// All joints of a simulated hand.
+const HAND_JOINTS: [Hand; 27] = [
+    Hand::Forearm,
+    Hand::Wrist,
+    Hand::Palm,
+    Hand::ThumbMetacarpal,
+    Hand::ThumbProximal,
+    Hand::ThumbDistal,
+    Hand::ThumbTip,
+    Hand::IndexMetacarpal,
+    Hand::IndexProximal,
+    Hand::IndexIntermediate,
+    Hand::IndexDistal,
+    Hand::IndexTip,
+    Hand::MiddleMetacarpal,
+    Hand::MiddleProximal,
+    Hand::MiddleIntermediate,
+    Hand::MiddleDistal,
+    Hand::MiddleTip,
+    Hand::RingMetacarpal,
+    Hand::RingProximal,
+    Hand::RingIntermediate,
+    Hand::RingDistal,
+    Hand::RingTip,
+    Hand::LittleMetacarpal,
+    Hand::LittleProximal,
+    Hand::LittleIntermediate,
+    Hand::LittleDistal,
+    Hand::LittleTip,
+];
+
+/// The [`XrBackend`] of the simulator, driven by the keyboard and mouse resources of the world.
+#[derive(Default)]
+pub struct XrSimulatorBackend {
+    /// The pose of the headset, [`None`] before the first frame.
+    headset: Option<Transform>,
+    presses: HashMap<XrControllerPress, f32>,
+    axes: HashMap<XrControllerAxis, f32>,
+    mouse_motion: ManualEventReader<MouseMotion>,
+    focus: ManualEventReader<WindowFocused>,
+    space_changes: ManualEventReader<XrReferenceSpaceChanged>,
+}
+
+impl XrBackend for XrSimulatorBackend {
+    fn name(&self) -> &str {
+        "simulator"
+    }
+
+    fn capabilities(&self) -> XrCapabilities {
+        XrCapabilities {
+            vr: true,
+            controllers: true,
+            hand_tracking: true,
+            ..default()
+        }
+    }
+
+    fn rig(&self) -> XrRigDescriptor {
+        XrRigDescriptor {
+            origin: XrOrigin::Room,
+            eyes: 1,
+            controllers: vec![XrController::Left, XrController::Right],
+            hands: true,
+            ..default()
+        }
+    }
+
+    fn update(&mut self, world: &World, frame: &mut XrBackendFrame) {
+        let settings = world
+            .get_resource::<XrSimulatorSettings>()
+            .cloned()
+            .unwrap_or_default();
+
+        if self.headset.is_none() {
+            self.headset = Some(Transform::from_xyz(0.0, settings.eye_height, 0.0));
+            for xr_controller in [XrController::Left, XrController::Right] {
+                frame.send_controller_event(XrControllerStateChangedEvent::new(
+                    xr_controller,
+                    XrControllerState::Tracking(XrControllerInfo {
+                        name: "XrSimulatorController".to_string(),
+                    }),
+                ));
+            }
+            frame.send_session_event(XrMode::VR);
+            frame.send_session_event(XrSessionState::Focused);
+        }
+
+        self.simulate_session(world, frame);
+        self.simulate_controller_input(world, frame);
+        let headset = self.simulate_headset(world, &settings);
+
+        frame.submit_pose(XrRigPart::Headset, headset, XrTrackingState::Tracked);
+        for (handedness, offset) in [
+            (Handedness::Left, -settings.ipd / 2.0),
+            (Handedness::Right, settings.ipd / 2.0),
+        ] {
+            let requested = settings.stereo || handedness == Handedness::Left;
+            frame.submit_pose(
+                XrRigPart::Eye(handedness, 0),
+                headset.mul_transform(Transform::from_xyz(offset, 0.0, 0.0)),
+                match requested {
+                    true => XrTrackingState::Tracked,
+                    false => XrTrackingState::NotRequested,
+                },
+            );
+        }
+
+        for (handedness, xr_controller, offset) in [
+            (
+                Handedness::Left,
+                XrController::Left,
+                Vec3::new(-0.2, -0.3, -0.4),
+            ),
+            (
+                Handedness::Right,
+                XrController::Right,
+                Vec3::new(0.2, -0.3, -0.4),
+            ),
+        ] {
+            let controller = headset.mul_transform(Transform::from_translation(offset));
+            frame.submit_pose(
+                XrRigPart::Controller(xr_controller),
+                controller,
+                XrTrackingState::Tracked,
+            );
+            self.simulate_hand(handedness, xr_controller, controller, &settings, frame);
+        }
+    }
+}
+
+impl XrSimulatorBackend {
+    /// Maps the focus of the primary window to the [`XrSessionState`].
+    fn simulate_session(&mut self, world: &World, frame: &mut XrBackendFrame) {
+        let Some(focus_events) = world.get_resource::<Events<WindowFocused>>() else {
+            return;
+        };
+        for focus_event in self.focus.read(focus_events) {
+            if world.get::<PrimaryWindow>(focus_event.window).is_none() {
+                continue;
+            }
+            if focus_event.focused {
+                frame.send_session_event(XrSessionState::Focused);
+            } else {
+                frame.send_session_event(XrSessionState::Visible);
+            }
+        }
+    }
+
+    /// Sends [`crate::controller_input::XrControllerEvent`]s for the keyboard and mouse input of the simulated controllers.
+    fn simulate_controller_input(&mut self, world: &World, frame: &mut XrBackendFrame) {
+        let (Some(keys), Some(mouse_buttons)) = (
+            world.get_resource::<Input<KeyCode>>(),
+            world.get_resource::<Input<MouseButton>>(),
+        ) else {
+            return;
+        };
+        let left_active = keys.pressed(KeyCode::AltLeft);
+
+        for xr_controller in [XrController::Left, XrController::Right] {
+            let active = left_active == (xr_controller == XrController::Left);
+
+            for (input_type, key) in PRESS_BINDINGS {
+                let pressed = active
+                    && match key {
+                        Some(key) => keys.pressed(key),
+                        None => mouse_buttons.pressed(MouseButton::Left),
+                    };
+                let value = if pressed { 1.0 } else { 0.0 };
+                let press = XrControllerPress::new(xr_controller, input_type);
+                if self.presses.insert(press, value) != Some(value) {
+                    frame.send_controller_event(XrControllerTouchChangedEvent::new(
+                        xr_controller,
+                        input_type,
+                        value,
+                    ));
+                    frame.send_controller_event(XrControllerPressChangedEvent::new(
+                        xr_controller,
+                        input_type,
+                        value,
+                    ));
+                }
+            }
+
+            let stick = if active {
+                Vec2::new(
+                    keys.pressed(KeyCode::Right) as u8 as f32
+                        - keys.pressed(KeyCode::Left) as u8 as f32,
+                    keys.pressed(KeyCode::Up) as u8 as f32
+                        - keys.pressed(KeyCode::Down) as u8 as f32,
+                )
+            } else {
+                Vec2::ZERO
+            };
+            for (axis_type, value) in [
+                (XrControllerAxisType::StickX, stick.x),
+                (XrControllerAxisType::StickY, stick.y),
+            ] {
+                let axis = XrControllerAxis::new(xr_controller, axis_type);
+                if self.axes.insert(axis, value) != Some(value) {
+                    frame.send_controller_event(XrControllerAxisChangedEvent::new(
+                        xr_controller,
+                        axis_type,
+                        value,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Moves and rotates the simulated headset and returns its pose.
+    fn simulate_headset(&mut self, world: &World, settings: &XrSimulatorSettings) -> Transform {
+        let mut transform = self.headset.unwrap_or_default();
+
+        // Like a runtime the simulator reports the headset in the new reference space after a switch, a recenter only moves the origin.
+        if let Some(space_changes) = world.get_resource::<Events<XrReferenceSpaceChanged>>() {
+            for space_change in self
+                .space_changes
+                .read(space_changes)
+                .filter(|space_change| space_change.previous != space_change.current)
+            {
+                let inverse_offset =
+                    Transform::from_matrix(space_change.offset.compute_matrix().inverse());
+                transform = inverse_offset.mul_transform(transform);
+            }
+        }
+
+        let mut look = Vec2::ZERO;
+        if let Some(mouse_motion) = world.get_resource::<Events<MouseMotion>>() {
+            for motion in self.mouse_motion.read(mouse_motion) {
+                look += motion.delta;
+            }
+        }
+        let looking = world
+            .get_resource::<Input<MouseButton>>()
+            .is_some_and(|mouse_buttons| mouse_buttons.pressed(MouseButton::Right));
+        if !looking {
+            look = Vec2::ZERO;
+        }
+
+        let mut movement = Vec3::ZERO;
+        if let Some(keys) = world.get_resource::<Input<KeyCode>>() {
+            for (key, direction) in [
+                (KeyCode::W, Vec3::NEG_Z),
+                (KeyCode::S, Vec3::Z),
+                (KeyCode::A, Vec3::NEG_X),
+                (KeyCode::D, Vec3::X),
+                (KeyCode::Q, Vec3::NEG_Y),
+                (KeyCode::E, Vec3::Y),
+            ] {
+                if keys.pressed(key) {
+                    movement += direction;
+                }
+            }
+        }
+        let delta_seconds = world
+            .get_resource::<Time>()
+            .map_or(0.0, |time| time.delta_seconds());
+
+        let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        let yaw = yaw - look.x * settings.look_sensitivity;
+        let pitch = (pitch - look.y * settings.look_sensitivity).clamp(-1.54, 1.54);
+        transform.rotation = Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0);
+        transform.translation += Quat::from_rotation_y(yaw)
+            * movement.normalize_or_zero()
+            * settings.move_speed
+            * delta_seconds;
+
+        self.headset = Some(transform);
+        transform
+    }
+
+    /// Submits the joints of a simulated hand behind its controller, the fingers curl with the trigger and the grip.
+    fn simulate_hand(
+        &self,
+        handedness: Handedness,
+        xr_controller: XrController,
+        controller: Transform,
+        settings: &XrSimulatorSettings,
+        frame: &mut XrBackendFrame,
+    ) {
+        if !settings.hands {
+            for hand in HAND_JOINTS {
+                frame.set_tracking_state(
+                    XrRigPart::HandJoint(handedness, hand),
+                    XrTrackingState::NotRequested,
+                );
+            }
+            return;
+        }
+
+        let wrist = controller.mul_transform(Transform::from_xyz(0.0, 0.0, 0.1));
+        let curled = |input_type| {
+            self.presses
+                .get(&XrControllerPress::new(xr_controller, input_type))
+                .is_some_and(|value| *value > 0.5)
+        };
+
+        for hand in HAND_JOINTS {
+            let local = match hand {
+                Hand::Forearm => Transform::from_xyz(0.0, 0.0, 0.25),
+                Hand::Wrist => Transform::IDENTITY,
+                Hand::Palm => Transform::from_xyz(0.0, 0.0, -0.05),
+                _ => {
+                    let Some((finger, joint)) = finger_joint_index(&hand) else {
+                        continue;
+                    };
+                    let input_type = match finger {
+                        1 => XrControllerInputType::Trigger,
+                        _ => XrControllerInputType::Grip,
+                    };
+                    let curl = if curled(input_type) { 1.0 } else { 0.0 };
+                    let mut joint_transform = finger_joint_transform(finger, joint, curl);
+                    if handedness == Handedness::Left {
+                        joint_transform.translation.x = -joint_transform.translation.x;
+                        let rotation = joint_transform.rotation;
+                        joint_transform.rotation =
+                            Quat::from_xyzw(rotation.x, -rotation.y, -rotation.z, rotation.w);
+                    }
+                    joint_transform
+                }
+            };
+            frame.submit_pose(
+                XrRigPart::HandJoint(handedness, hand),
+                wrist.mul_transform(local),
+                XrTrackingState::Tracked,
+            );
+        }
+    }
+}
+
+/// Marks the rig spawned for the [`XrSimulatorBackend`] with [`XrSimulated`].
+pub fn mark_simulated_rig(runtime: Res<XrRuntime>, mut commands: Commands) {
+    for entity in runtime
+        .origin()
+        .into_iter()
+        .chain(runtime.parts().map(|(_, entity)| entity))
+    {
+        commands.entity(entity).insert(XrSimulated);
+    }
+}
+
+/// The metacarpal and knuckle positions of each finger of a right hand relative to the wrist followed by the lengths of the remaining phalanges and the local curl axis.
+const FINGER_LAYOUT: [(Vec3, Vec3, &[f32], Vec3); 5] = [
+    (
+        Vec3::new(-0.02, -0.01, -0.02),
+        Vec3::new(-0.04, -0.01, -0.05),
+        &[0.032, 0.025],
+        Vec3::Y,
+    ),
+    (
+        Vec3::new(-0.022, 0.0, -0.01),
+        Vec3::new(-0.022, 0.0, -0.085),
+        &[0.04, 0.024, 0.022],
+        Vec3::X,
+    ),
+    (
+        Vec3::new(-0.002, 0.0, -0.01),
+        Vec3::new(-0.002, 0.0, -0.09),
+        &[0.045, 0.028, 0.024],
+        Vec3::X,
+    ),
+    (
+        Vec3::new(0.017, 0.0, -0.01),
+        Vec3::new(0.017, 0.0, -0.085),
+        &[0.042, 0.026, 0.022],
+        Vec3::X,
+    ),
+    (
+        Vec3::new(0.033, 0.0, -0.01),
+        Vec3::new(0.033, 0.0, -0.075),
+        &[0.032, 0.02, 0.02],
+        Vec3::X,
+    ),
+];
+
+/// Returns the finger and the joint index along the finger of a [`Hand`] joint.
+fn finger_joint_index(hand: &Hand) -> Option<(usize, usize)> {
+    match hand {
+        Hand::ThumbMetacarpal => Some((0, 0)),
+        Hand::ThumbProximal => Some((0, 1)),
+        Hand::ThumbDistal => Some((0, 2)),
+        Hand::ThumbTip => Some((0, 3)),
+        Hand::IndexMetacarpal => Some((1, 0)),
+        Hand::IndexProximal => Some((1, 1)),
+        Hand::IndexIntermediate => Some((1, 2)),
+        Hand::IndexDistal => Some((1, 3)),
+        Hand::IndexTip => Some((1, 4)),
+        Hand::MiddleMetacarpal => Some((2, 0)),
+        Hand::MiddleProximal => Some((2, 1)),
+        Hand::MiddleIntermediate => Some((2, 2)),
+        Hand::MiddleDistal => Some((2, 3)),
+        Hand::MiddleTip => Some((2, 4)),
+        Hand::RingMetacarpal => Some((3, 0)),
+        Hand::RingProximal => Some((3, 1)),
+        Hand::RingIntermediate => Some((3, 2)),
+        Hand::RingDistal => Some((3, 3)),
+        Hand::RingTip => Some((3, 4)),
+        Hand::LittleMetacarpal => Some((4, 0)),
+        Hand::LittleProximal => Some((4, 1)),
+        Hand::LittleIntermediate => Some((4, 2)),
+        Hand::LittleDistal => Some((4, 3)),
+        Hand::LittleTip => Some((4, 4)),
+        Hand::Forearm | Hand::Wrist | Hand::Palm => None,
+    }
+}
+
+/// Returns the transform of a finger joint of a right hand relative to the wrist.
+fn finger_joint_transform(finger: usize, joint: usize, curl: f32) -> Transform {
+    let (metacarpal, knuckle, lengths, axis) = FINGER_LAYOUT[finger];
+    let base = Quat::from_rotation_arc(Vec3::NEG_Z, (knuckle - metacarpal).normalize());
+    if joint == 0 {
+        return Transform::from_translation(metacarpal).with_rotation(base);
+    }
+
+    let step = Quat::from_axis_angle(axis, -curl * FRAC_PI_3);
+    let mut translation = knuckle;
+    let mut rotation = base * step;
+    for length in lengths.iter().take(joint - 1) {
+        translation += rotation * Vec3::NEG_Z * *length;
+        rotation *= step;
+    }
+    Transform::from_translation(translation).with_rotation(rotation)
+}
+
+/// Renders the simulated eyes to the primary window, side by side if [`XrSimulatorSettings::stereo`] is enabled.
+///
+/// The tracking states of the eyes are submitted by the [`XrSimulatorBackend`].
+#[allow(clippy::type_complexity)]
+pub fn simulate_viewports(
+    settings: Res<XrSimulatorSettings>,
+    window: Query<Ref<Window>, With<PrimaryWindow>>,
+    mut eyes: Query<
+        (&mut Camera, &mut XrViewConfig, &Handedness),
+        (With<XrSimulated>, With<XrEye>),
+    >,
+) {
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+    if !(settings.is_changed() || window.is_changed()) {
+        return;
+    }
+
+    let size = UVec2::new(window.physical_width() / 2, window.physical_height());
+    for (mut camera, mut config, handedness) in eyes.iter_mut() {
+        let (active, viewport) = match (settings.stereo, handedness) {
+            (true, Handedness::Left) => (
+                true,
+                Some(Viewport {
+                    physical_position: UVec2::ZERO,
+                    physical_size: size,
+                    ..default()
+                }),
+            ),
+            (true, Handedness::Right) => (
+                true,
+                Some(Viewport {
+                    physical_position: UVec2::new(size.x, 0),
+                    physical_size: size,
+                    ..default()
+                }),
+            ),
+            (false, Handedness::Left) => (true, None),
+            (false, Handedness::Right) => (false, None),
+        };
+        let resolution = viewport.as_ref().map_or(
+            UVec2::new(window.physical_width(), window.physical_height()),
+            |viewport| viewport.physical_size,
+        );
+        config.set_if_neq(XrViewConfig {
+            fov: XrFov::from_vertical(
+                std::f32::consts::FRAC_PI_4,
+                resolution.x as f32 / resolution.y.max(1) as f32,
+            ),
+            recommended_resolution: resolution,
+        });
+        camera.is_active = active;
+        camera.viewport = viewport;
+    }
+}
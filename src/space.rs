@@ -1,7 +1,13 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 
 pub use crate::XrActive;
 pub use crate::XrLocal;
+pub use crate::XrTrackingState;
+use crate::{
+    boundary::XrBoundary,
+    head::{XrHead, XrHeadset},
+    XrView,
+};
 
 /// The defining [`Component`] for head entities,
 /// Represents the origin transform of the xr runtime and all tracked xr entities are relative to this transform.
@@ -9,7 +15,13 @@ pub use crate::XrLocal;
 /// This transform can be moved to move the whole xr space.
 ///
 /// This component should be spawned including a  [`SpatialBundle`] or similar and a [`XrActive`] component.
-#[derive(Clone, Copy, Component)]
+#[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub enum XrOrigin {
     /// For an origin that is positioned at the head of the person.
     View,
@@ -21,13 +33,22 @@ pub enum XrOrigin {
     Other,
 }
 
+/// Reflection inserts an origin without specified locality and then applies the reflected one.
+impl FromWorld for XrOrigin {
+    fn from_world(_world: &mut World) -> Self {
+        Self::Other
+    }
+}
+
 #[derive(Bundle)]
 pub struct XrOriginBundle {
     name: Name,
     spatial_bundle: SpatialBundle,
     xr_origin: XrOrigin,
+    xr_world_scale: XrWorldScale,
     xr_local: XrLocal,
     xr_active: XrActive,
+    xr_tracking_state: XrTrackingState,
 }
 
 impl XrOriginBundle {
@@ -36,18 +57,562 @@ impl XrOriginBundle {
             name: Name::new("XrOrigin"),
             spatial_bundle: SpatialBundle::default(),
             xr_origin: origin_type,
+            xr_world_scale: XrWorldScale::default(),
             xr_local: XrLocal,
             xr_active: XrActive(true),
+            xr_tracking_state: XrTrackingState::Tracked,
         }
     }
 }
 
+/// The scale of the tracking space of a [`XrOrigin`], e.g. to shrink the user to explore a miniature model.
+///
+/// A scale of 2 makes the user a giant: the origin is scaled uniformly, so the eye separation, hands and controllers grow with it,
+/// and the distances and speeds of this crate are in meters of the tracking space, e.g. locomotion and teleport arcs.
+/// The point on the floor below the headset keeps its world position when the scale changes.
+///
+/// Change this component instead of the scale of the [`Transform`] of the origin, which is reset to the world scale whenever it differs.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrWorldScale(pub f32);
+
+impl Default for XrWorldScale {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// The heads and headsets of the local rig.
+type XrLocalHeadQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static GlobalTransform, Has<XrHead>),
+    (Or<(With<XrHead>, With<XrHeadset>)>, With<XrLocal>),
+>;
+
+/// A [`SystemParam`] converting between world space, the tracking space of the local [`XrOrigin`] and the spaces of the local head and views.
+///
+/// The tracking space is the space of the children of the origin, so it includes the [`XrWorldScale`].
+/// All functions return [`None`] if the local rig has no such entity or, for the origin, more than one local origin.
+#[derive(SystemParam)]
+pub struct XrSpaces<'w, 's> {
+    origins: Query<'w, 's, &'static GlobalTransform, (With<XrOrigin>, With<XrLocal>)>,
+    heads: XrLocalHeadQuery<'w, 's>,
+    views:
+        Query<'w, 's, (&'static XrView, &'static Camera, &'static GlobalTransform), With<XrLocal>>,
+}
+
+impl<'w, 's> XrSpaces<'w, 's> {
+    /// The transform of the local [`XrOrigin`].
+    pub fn origin(&self) -> Option<&GlobalTransform> {
+        self.origins.get_single().ok()
+    }
+
+    /// The transform of the local [`XrHead`] and falls back to the [`XrHeadset`].
+    pub fn head(&self) -> Option<&GlobalTransform> {
+        self.heads
+            .iter()
+            .max_by_key(|(_, is_head)| *is_head)
+            .map(|(transform, _)| transform)
+    }
+
+    /// The transform and the [`Camera`] of the local [`XrView`] with the index.
+    pub fn view(&self, view: u8) -> Option<(&GlobalTransform, &Camera)> {
+        self.views
+            .iter()
+            .find(|(xr_view, _, _)| xr_view.0 == view)
+            .map(|(_, camera, transform)| (transform, camera))
+    }
+
+    /// Converts a point in world space into the tracking space of the local [`XrOrigin`].
+    pub fn world_to_tracking(&self, point: Vec3) -> Option<Vec3> {
+        self.origin()
+            .map(|origin| origin.affine().inverse().transform_point3(point))
+    }
+
+    /// Converts a point in the tracking space of the local [`XrOrigin`] into world space.
+    pub fn tracking_to_world(&self, point: Vec3) -> Option<Vec3> {
+        self.origin().map(|origin| origin.transform_point(point))
+    }
+
+    /// Converts a transform in world space into the tracking space of the local [`XrOrigin`].
+    pub fn world_to_tracking_transform(&self, transform: &GlobalTransform) -> Option<Transform> {
+        self.origin().map(|origin| transform.reparented_to(origin))
+    }
+
+    /// Converts a transform in the tracking space of the local [`XrOrigin`] into world space.
+    pub fn tracking_to_world_transform(&self, transform: &Transform) -> Option<GlobalTransform> {
+        self.origin().map(|origin| origin.mul_transform(*transform))
+    }
+
+    /// Converts a point in world space into the space of the local head, e.g. to place content in front of the user.
+    pub fn head_relative(&self, point: Vec3) -> Option<Vec3> {
+        self.head()
+            .map(|head| head.affine().inverse().transform_point3(point))
+    }
+
+    /// Converts a point in world space into the space of the local [`XrView`] with the index.
+    pub fn world_to_view(&self, view: u8, point: Vec3) -> Option<Vec3> {
+        self.view(view)
+            .map(|(transform, _)| transform.affine().inverse().transform_point3(point))
+    }
+
+    /// Returns the ray in world space through the normalized device coordinates of the local [`XrView`] with the index.
+    ///
+    /// The ray starts at the near plane, `ndc` ranges from -1 to 1 with `y` pointing up.
+    pub fn view_ray(&self, view: u8, ndc: Vec2) -> Option<Ray> {
+        let (transform, camera) = self.view(view)?;
+        let near = camera.ndc_to_world(transform, ndc.extend(1.0))?;
+        // An ndc with a z of 0 is at infinity for the infinite reverse projection.
+        let far = camera.ndc_to_world(transform, ndc.extend(f32::EPSILON))?;
+        Some(Ray {
+            origin: near,
+            direction: (far - near).normalize(),
+        })
+    }
+}
+
+/// Settings for switching between [`XrOrigin`] kinds.
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Debug, Default)]
+pub struct XrSpaceSettings {
+    /// The assumed height of the headset above the floor in meters when switching from a [`XrOrigin::View`] to an origin on the floor.
+    ///
+    /// Also places the floor below a [`XrOrigin::View`] when teleporting, see [`crate::teleport`].
+    pub view_height: f32,
+}
+
+impl Default for XrSpaceSettings {
+    fn default() -> Self {
+        Self { view_height: 1.6 }
+    }
+}
+
+/// An [`Event`] requesting to move the [`XrOrigin`] so the headset is above the `target`.
+///
+/// The point below the headset at the height of the origin is moved to the translation of the `target` and the yaw of the headset is aligned with the yaw of the `target`.
+/// The `target` is relative to the parent of the origin, usually the world.
+#[derive(Event, Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrRecenterRequest {
+    pub origin: Entity,
+    pub target: Transform,
+}
+
+/// An [`Event`] requesting to switch the kind of the [`XrOrigin`] while all content keeps its world pose.
+///
+/// The new origin is estimated from the headset: [`XrOrigin::View`] is placed at the headset, [`XrOrigin::Seat`] on the floor below it, both with the yaw of the headset.
+/// [`XrOrigin::Room`] is placed at the center of a [`XrBoundary`] child of the origin or on the floor below the headset and [`XrOrigin::Other`] keeps the current pose.
+#[derive(Event, Debug, Copy, Clone, PartialEq, Eq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrOriginSwitchRequest {
+    pub origin: Entity,
+    pub kind: XrOrigin,
+}
+
+/// An [`Event`] sent whenever a [`XrOrigin`] was recentered or switched to another kind.
+///
+/// The transform of the origin was multiplied by the `offset`.
+/// For a switch the children of the origin were moved by the inverse of the `offset`, so they keep their world pose, and xr platform specific crates should report the following poses relative to the `current` reference space.
+/// For a recenter `previous` and `current` are equal and only the origin was moved.
+#[derive(Event, Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrReferenceSpaceChanged {
+    pub origin: Entity,
+    pub previous: XrOrigin,
+    pub current: XrOrigin,
+    /// The pose of the new reference space relative to the previous one.
+    pub offset: Transform,
+}
+
+fn inverse(transform: &Transform) -> Transform {
+    Transform::from_matrix(transform.compute_matrix().inverse())
+}
+
+fn yaw(rotation: Quat) -> Quat {
+    Quat::from_rotation_y(rotation.to_euler(EulerRot::YXZ).0)
+}
+
+/// Moves the [`XrOrigin`]s of the [`XrRecenterRequest`]s.
+pub fn recenter_origins(
+    mut requests: EventReader<XrRecenterRequest>,
+    mut origins: Query<(&XrOrigin, &mut Transform, &Children)>,
+    headsets: Query<&Transform, (With<XrHeadset>, Without<XrOrigin>)>,
+    mut changed_events: EventWriter<XrReferenceSpaceChanged>,
+) {
+    for request in requests.read() {
+        let Ok((xr_origin, mut transform, children)) = origins.get_mut(request.origin) else {
+            continue;
+        };
+        let headset = children
+            .iter()
+            .find_map(|child| headsets.get(*child).ok())
+            .copied()
+            .unwrap_or_default();
+
+        let head = Transform::from_translation(
+            Vec3::new(headset.translation.x, 0.0, headset.translation.z) * transform.scale,
+        )
+        .with_rotation(yaw(headset.rotation));
+        let target = Transform::from_translation(request.target.translation)
+            .with_rotation(yaw(request.target.rotation));
+        let recentered = target
+            .mul_transform(inverse(&head))
+            .with_scale(transform.scale);
+        let offset = inverse(&transform).mul_transform(recentered);
+        *transform = recentered;
+
+        changed_events.send(XrReferenceSpaceChanged {
+            origin: request.origin,
+            previous: *xr_origin,
+            current: *xr_origin,
+            offset,
+        });
+    }
+}
+
+/// Switches the kind of the [`XrOrigin`]s of the [`XrOriginSwitchRequest`]s.
+#[allow(clippy::type_complexity)]
+pub fn switch_origins(
+    settings: Res<XrSpaceSettings>,
+    mut requests: EventReader<XrOriginSwitchRequest>,
+    mut origins: Query<(&mut XrOrigin, &mut Transform, &Children)>,
+    mut children: Query<(&mut Transform, Has<XrHeadset>, Option<&XrBoundary>), Without<XrOrigin>>,
+    mut changed_events: EventWriter<XrReferenceSpaceChanged>,
+) {
+    for request in requests.read() {
+        let Ok((mut xr_origin, mut transform, origin_children)) = origins.get_mut(request.origin)
+        else {
+            continue;
+        };
+
+        let mut headset = Transform::default();
+        let mut boundary_center = None;
+        for child in origin_children.iter() {
+            let Ok((child_transform, is_headset, boundary)) = children.get(*child) else {
+                continue;
+            };
+            if is_headset {
+                headset = *child_transform;
+            }
+            if let Some(boundary) = boundary.filter(|boundary| !boundary.points.is_empty()) {
+                let center = boundary.points.iter().sum::<Vec2>() / boundary.points.len() as f32;
+                boundary_center = Some(
+                    Transform::from_translation(
+                        child_transform.transform_point(Vec3::new(center.x, 0.0, center.y)),
+                    )
+                    .with_rotation(yaw(child_transform.rotation)),
+                );
+            }
+        }
+
+        let floor = if *xr_origin == XrOrigin::View {
+            -settings.view_height
+        } else {
+            0.0
+        };
+        let below_headset =
+            Transform::from_xyz(headset.translation.x, floor, headset.translation.z);
+        let offset = match request.kind {
+            XrOrigin::View => Transform::from_translation(headset.translation)
+                .with_rotation(yaw(headset.rotation)),
+            XrOrigin::Seat => below_headset.with_rotation(yaw(headset.rotation)),
+            XrOrigin::Room => boundary_center.unwrap_or(below_headset),
+            XrOrigin::Other => Transform::IDENTITY,
+        };
+
+        let inverse_offset = inverse(&offset);
+        *transform = transform.mul_transform(offset);
+        for child in origin_children.iter() {
+            if let Ok((mut child_transform, _, _)) = children.get_mut(*child) {
+                *child_transform = inverse_offset.mul_transform(*child_transform);
+            }
+        }
+
+        let previous = *xr_origin;
+        *xr_origin = request.kind;
+        changed_events.send(XrReferenceSpaceChanged {
+            origin: request.origin,
+            previous,
+            current: request.kind,
+            offset,
+        });
+    }
+}
+
+/// Applies the [`XrWorldScale`] to the [`XrOrigin`]s whose scale differs while the point on the floor below the headset keeps its position.
+#[allow(clippy::type_complexity)]
+pub fn apply_world_scale(
+    mut origins: Query<(Ref<XrWorldScale>, &mut Transform, Option<&Children>), With<XrOrigin>>,
+    headsets: Query<&Transform, (With<XrHeadset>, Without<XrOrigin>)>,
+) {
+    for (world_scale, mut transform, children) in origins.iter_mut() {
+        let scale = Vec3::splat(world_scale.0);
+        if transform.scale == scale {
+            continue;
+        }
+        if world_scale.is_added() {
+            transform.scale = scale;
+            continue;
+        }
+        let headset = children
+            .into_iter()
+            .flatten()
+            .find_map(|child| headsets.get(*child).ok())
+            .copied()
+            .unwrap_or_default();
+        let pivot = Vec3::new(headset.translation.x, 0.0, headset.translation.z);
+
+        let before = transform.transform_point(pivot);
+        transform.scale = scale;
+        let after = transform.transform_point(pivot);
+        transform.translation += before - after;
+    }
+}
+
 #[cfg(notes)]
 mod notes {
     /// Is there other information obtainable of the space a xr session is taking place in?
     pub struct XrSpace;
-    /// Intended for chaperone, guardian geometry. Could be a SpacialBundle with the index indication the index of the polygon vertx.
-    pub struct XrBoundary(usize);
     /// Apple room scanning might find place here.
     pub struct XrRoom;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, TransformPlugin, HierarchyPlugin))
+            .init_resource::<XrSpaceSettings>()
+            .add_event::<XrRecenterRequest>()
+            .add_event::<XrOriginSwitchRequest>()
+            .add_event::<XrReferenceSpaceChanged>()
+            .add_systems(Update, (recenter_origins, switch_origins));
+        app
+    }
+
+    fn spawn_origin(app: &mut App, kind: XrOrigin, transform: Transform) -> (Entity, Entity) {
+        let headset = app
+            .world
+            .spawn((
+                XrHeadset,
+                SpatialBundle::from_transform(
+                    Transform::from_xyz(0.5, 1.6, -0.3).with_rotation(Quat::from_euler(
+                        EulerRot::YXZ,
+                        0.7,
+                        -0.2,
+                        0.1,
+                    )),
+                ),
+            ))
+            .id();
+        let origin = app
+            .world
+            .spawn((kind, SpatialBundle::from_transform(transform)))
+            .add_child(headset)
+            .id();
+        (origin, headset)
+    }
+
+    fn world_yaw(transform: &GlobalTransform) -> f32 {
+        transform
+            .compute_transform()
+            .rotation
+            .to_euler(EulerRot::YXZ)
+            .0
+    }
+
+    #[test]
+    fn recenter_moves_headset_above_target() {
+        for scale in [1.0, 2.0] {
+            let mut app = app();
+            let (origin, headset) = spawn_origin(
+                &mut app,
+                XrOrigin::Seat,
+                Transform::from_xyz(1.0, 0.0, 2.0)
+                    .with_rotation(Quat::from_rotation_y(0.3))
+                    .with_scale(Vec3::splat(scale)),
+            );
+            app.world.send_event(XrRecenterRequest {
+                origin,
+                target: Transform::from_xyz(4.0, 0.0, -1.0)
+                    .with_rotation(Quat::from_rotation_y(1.2)),
+            });
+            app.update();
+
+            let headset = app.world.get::<GlobalTransform>(headset).unwrap();
+            let translation = headset.translation();
+            assert!((translation.x - 4.0).abs() < 1e-5, "{translation}");
+            assert!((translation.y - 1.6 * scale).abs() < 1e-5, "{translation}");
+            assert!((translation.z + 1.0).abs() < 1e-5, "{translation}");
+            assert!((world_yaw(headset) - 1.2).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn switch_keeps_world_pose_of_content() {
+        for kind in [
+            XrOrigin::View,
+            XrOrigin::Seat,
+            XrOrigin::Room,
+            XrOrigin::Other,
+        ] {
+            let mut app = app();
+            let (origin, headset) = spawn_origin(
+                &mut app,
+                XrOrigin::View,
+                Transform::from_xyz(1.0, 1.6, 2.0).with_rotation(Quat::from_rotation_y(0.3)),
+            );
+            let content = app
+                .world
+                .spawn(SpatialBundle::from_transform(
+                    Transform::from_xyz(-1.0, 0.5, 1.0).with_rotation(Quat::from_rotation_x(0.4)),
+                ))
+                .set_parent(origin)
+                .id();
+            app.update();
+            let before = [headset, content].map(|entity| {
+                app.world
+                    .get::<GlobalTransform>(entity)
+                    .unwrap()
+                    .compute_transform()
+            });
+
+            app.world.send_event(XrOriginSwitchRequest { origin, kind });
+            app.update();
+
+            assert_eq!(*app.world.get::<XrOrigin>(origin).unwrap(), kind);
+            for (entity, before) in [headset, content].into_iter().zip(before) {
+                let after = app
+                    .world
+                    .get::<GlobalTransform>(entity)
+                    .unwrap()
+                    .compute_transform();
+                assert!(
+                    after.translation.abs_diff_eq(before.translation, 1e-5),
+                    "{kind:?}"
+                );
+                assert!(
+                    after.rotation.abs_diff_eq(before.rotation, 1e-5),
+                    "{kind:?}"
+                );
+            }
+            if kind == XrOrigin::Seat {
+                let origin = app.world.get::<GlobalTransform>(origin).unwrap();
+                assert!(origin.translation().y.abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn world_scale_scales_rig() {
+        use crate::{
+            handedness::{Handedness, LeftHanded, RightHanded},
+            hands::{hand_joint::Wrist, HandJointBundle, HandJointRadius},
+            head::{XrEye, XrEyeBundle},
+        };
+
+        let mut app = app();
+        app.add_systems(Update, apply_world_scale);
+        let origin = app
+            .world
+            .spawn(XrOriginBundle::default(XrOrigin::Room))
+            .id();
+        let headset = app
+            .world
+            .spawn((
+                XrHeadset,
+                SpatialBundle::from_transform(Transform::from_xyz(1.0, 1.6, 0.5)),
+            ))
+            .set_parent(origin)
+            .id();
+        let left = XrView::eye(0, Handedness::Left).unwrap();
+        let right = XrView::eye(0, Handedness::Right).unwrap();
+        for (eye, x) in [
+            (
+                app.world
+                    .spawn(XrEyeBundle::<LeftHanded>::default(0, left))
+                    .id(),
+                -0.032,
+            ),
+            (
+                app.world
+                    .spawn(XrEyeBundle::<RightHanded>::default(0, right))
+                    .id(),
+                0.032,
+            ),
+        ] {
+            app.world
+                .entity_mut(eye)
+                .insert(Transform::from_xyz(1.0 + x, 1.6, 0.5))
+                .set_parent(origin);
+        }
+        let wrist = app
+            .world
+            .spawn(HandJointBundle::<RightHanded, Wrist>::default())
+            .insert(HandJointRadius(Some(0.02)))
+            .set_parent(origin)
+            .id();
+        app.update();
+        let floor = Vec3::new(1.0, 0.0, 0.5);
+        assert_eq!(
+            app.world
+                .get::<GlobalTransform>(headset)
+                .unwrap()
+                .translation(),
+            Vec3::new(1.0, 1.6, 0.5)
+        );
+
+        app.world.get_mut::<XrWorldScale>(origin).unwrap().0 = 0.1;
+        app.update();
+        let eyes: Vec<Vec3> = app
+            .world
+            .query_filtered::<&GlobalTransform, With<XrEye>>()
+            .iter(&app.world)
+            .map(|eye| eye.translation())
+            .collect();
+        assert!((eyes[0].distance(eyes[1]) - 0.0064).abs() < 1e-6);
+        let wrist_scale = app
+            .world
+            .get::<GlobalTransform>(wrist)
+            .unwrap()
+            .compute_transform()
+            .scale;
+        let radius = app.world.get::<HandJointRadius>(wrist).unwrap().0.unwrap();
+        assert!((radius * wrist_scale.x - 0.002).abs() < 1e-6);
+        let origin_transform = app.world.get::<GlobalTransform>(origin).unwrap();
+        assert!(origin_transform
+            .transform_point(floor)
+            .abs_diff_eq(floor, 1e-5));
+
+        // A scale overwritten by other code is reset to the world scale.
+        app.world.get_mut::<Transform>(origin).unwrap().scale = Vec3::ONE;
+        app.update();
+        assert_eq!(
+            app.world.get::<Transform>(origin).unwrap().scale,
+            Vec3::splat(0.1)
+        );
+    }
+}
@@ -0,0 +1,336 @@
+//! A spectator camera showing the xr experience on the desktop window, for streaming and for observers during user testing.
+//!
+//! The [`XrSpectator`] camera follows the local rig in one of three [`XrSpectatorMode`]s:
+//! a cropped and stabilized copy of a [`XrEye`], a smoothed first person view with a lower field of view, or a third person view of the user.
+//!
+//! The [`XrSpectatorPlugin`] visualizes the headset, the controllers and the hands with simple meshes on the [`XrSpectatorSettings::rig_layer`].
+//! Only the spectator renders this layer, so the rig is not visible in the headset.
+//!
+//! The rig is only visualized if the [`bevy::pbr::PbrPlugin`] is added.
+
+use bevy::{
+    prelude::*,
+    render::view::{RenderLayers, VisibilitySystems},
+    transform::TransformSystem,
+    utils::HashSet,
+};
+
+use crate::{
+    controller::XrController,
+    hands::Hand,
+    head::{XrEye, XrHead, XrHeadset},
+    projection::XrViewConfig,
+    space::XrOrigin,
+    XrActive, XrLocal, XrView,
+};
+
+pub struct XrSpectatorPlugin;
+
+impl Plugin for XrSpectatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrSpectatorSettings>()
+            .add_systems(
+                PostUpdate,
+                update_spectators
+                    .after(TransformSystem::TransformPropagate)
+                    .before(VisibilitySystems::UpdatePerspectiveFrusta),
+            )
+            .add_systems(Update, (spawn_rig_visuals, update_rig_visuals).chain());
+
+        app.register_type::<XrSpectator>()
+            .register_type::<XrSpectatorMode>()
+            .register_type::<XrSpectatorSettings>()
+            .register_type::<XrRigVisual>();
+    }
+}
+
+/// Settings of the [`XrSpectatorPlugin`].
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Debug, Default)]
+pub struct XrSpectatorSettings {
+    /// Is the rig visualized for the spectator?
+    pub show_rig: bool,
+    /// The [`RenderLayers`] layer of the rig visualization, rendered by the [`XrSpectator`] cameras only.
+    pub rig_layer: u8,
+}
+
+impl Default for XrSpectatorSettings {
+    fn default() -> Self {
+        Self {
+            show_rig: true,
+            rig_layer: 31,
+        }
+    }
+}
+
+/// What the [`XrSpectator`] shows.
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrSpectatorMode {
+    /// The view of the [`XrEye`] with the [`XrView`] index, cropped to the fraction of its vertical field of view.
+    ///
+    /// Only the rotation is smoothed, so the image is stabilized without lagging behind the movement of the user.
+    Eye { view: u8, crop: f32 },
+    /// The view of the [`XrHead`] with the vertical field of view in radians, smoothed and leveled to the horizon.
+    FirstPerson { fov: f32 },
+    /// A view of the user from the offset to the [`XrHead`] in the space of the [`XrOrigin`], the field of view of the camera is kept.
+    ThirdPerson { offset: Vec3 },
+}
+
+impl Default for XrSpectatorMode {
+    fn default() -> Self {
+        Self::FirstPerson {
+            fov: 60f32.to_radians(),
+        }
+    }
+}
+
+/// The defining [`Component`] for spectator cameras.
+///
+/// This component should be spawned including a [`Camera3dBundle`] with a [`PerspectiveProjection`], see [`XrSpectatorBundle`].
+/// The spectator is not parented to the rig, its transform is set in world space.
+#[derive(Component, Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrSpectator {
+    pub mode: XrSpectatorMode,
+    /// The time in seconds the camera takes to follow about two thirds of a movement, `0` disables the smoothing.
+    pub smoothing: f32,
+}
+
+impl Default for XrSpectator {
+    fn default() -> Self {
+        Self {
+            mode: XrSpectatorMode::default(),
+            smoothing: 0.2,
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct XrSpectatorBundle {
+    name: Name,
+    camera_bundle: Camera3dBundle,
+    render_layers: RenderLayers,
+    xr_spectator: XrSpectator,
+}
+
+impl XrSpectatorBundle {
+    /// A spectator rendering to the primary window after all other cameras, including the rig visualization on the layer.
+    pub fn default(mode: XrSpectatorMode, rig_layer: u8) -> Self {
+        Self {
+            name: Name::new("XrSpectator"),
+            camera_bundle: Camera3dBundle {
+                camera: Camera {
+                    order: isize::MAX,
+                    ..default()
+                },
+                ..default()
+            },
+            render_layers: RenderLayers::layer(0).with(rig_layer),
+            xr_spectator: XrSpectator { mode, ..default() },
+        }
+    }
+}
+
+/// Marks the meshes visualizing the rig for the [`XrSpectator`].
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrRigVisual;
+
+/// Moves the [`XrSpectator`] cameras after the transforms of the rig are propagated.
+///
+/// The [`GlobalTransform`] of the spectator is written as well, so the camera does not lag a frame behind the rig.
+#[allow(clippy::type_complexity)]
+pub fn update_spectators(
+    time: Res<Time>,
+    mut spectators: Query<
+        (
+            Ref<XrSpectator>,
+            &mut Transform,
+            &mut GlobalTransform,
+            &mut Projection,
+        ),
+        Without<XrView>,
+    >,
+    eyes: Query<(&XrView, &GlobalTransform, Option<&XrViewConfig>), (With<XrEye>, With<XrLocal>)>,
+    heads: Query<
+        (&GlobalTransform, Has<XrHead>),
+        (
+            Or<(With<XrHead>, With<XrHeadset>)>,
+            With<XrLocal>,
+            Without<XrSpectator>,
+        ),
+    >,
+    origins: Query<&GlobalTransform, (With<XrOrigin>, With<XrLocal>, Without<XrSpectator>)>,
+) {
+    // The head bone is preferred over the headset.
+    let head = heads
+        .iter()
+        .max_by_key(|(_, is_head)| *is_head)
+        .map(|(transform, _)| transform.compute_transform());
+    let origin = origins
+        .iter()
+        .next()
+        .map(|transform| transform.compute_transform());
+
+    for (spectator, mut transform, mut global_transform, mut projection) in spectators.iter_mut() {
+        // A new spectator starts at its target instead of flying in.
+        let blend = match spectator.smoothing > 0.0 && !spectator.is_added() {
+            true => 1.0 - (-time.delta_seconds() / spectator.smoothing).exp(),
+            false => 1.0,
+        };
+
+        let (target, fov) = match spectator.mode {
+            XrSpectatorMode::Eye { view, crop } => {
+                let Some((_, eye, config)) = eyes.iter().find(|(xr_view, _, _)| xr_view.0 == view)
+                else {
+                    continue;
+                };
+                let eye = eye.compute_transform();
+                let fov = config.map_or(90f32.to_radians(), |config| config.fov.vertical());
+                let rotation = transform.rotation.slerp(eye.rotation, blend);
+                (
+                    Transform::from_translation(eye.translation).with_rotation(rotation),
+                    Some(fov * crop),
+                )
+            }
+            XrSpectatorMode::FirstPerson { fov } => {
+                let Some(head) = head else {
+                    continue;
+                };
+                let (yaw, pitch, _) = head.rotation.to_euler(EulerRot::YXZ);
+                let leveled = Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.0);
+                (
+                    Transform::from_translation(
+                        transform.translation.lerp(head.translation, blend),
+                    )
+                    .with_rotation(transform.rotation.slerp(leveled, blend)),
+                    Some(fov),
+                )
+            }
+            XrSpectatorMode::ThirdPerson { offset } => {
+                let (Some(head), Some(origin)) = (head, origin) else {
+                    continue;
+                };
+                let position = head.translation + origin.rotation * (offset * origin.scale);
+                let translation = transform.translation.lerp(position, blend);
+                let looking = Transform::from_translation(translation)
+                    .looking_at(head.translation, origin.up());
+                (
+                    looking.with_rotation(transform.rotation.slerp(looking.rotation, blend)),
+                    None,
+                )
+            }
+        };
+
+        *transform = target;
+        *global_transform = GlobalTransform::from(target);
+        if let (Some(fov), Projection::Perspective(perspective)) = (fov, projection.as_mut()) {
+            if perspective.fov != fov {
+                perspective.fov = fov;
+            }
+        }
+    }
+}
+
+/// Spawns the meshes visualizing the local headset, controllers and hands on the [`XrSpectatorSettings::rig_layer`].
+#[allow(clippy::type_complexity)]
+pub fn spawn_rig_visuals(
+    mut commands: Commands,
+    settings: Res<XrSpectatorSettings>,
+    meshes: Option<ResMut<Assets<Mesh>>>,
+    materials: Option<ResMut<Assets<StandardMaterial>>>,
+    parts: Query<
+        (Entity, Has<XrHeadset>, Has<XrController>),
+        (
+            Or<(With<XrHeadset>, With<XrController>, With<Hand>)>,
+            With<XrLocal>,
+        ),
+    >,
+    mut visualized: Local<HashSet<Entity>>,
+    mut assets: Local<Option<[(Handle<Mesh>, Handle<StandardMaterial>); 3]>>,
+) {
+    let (Some(mut meshes), Some(mut materials)) = (meshes, materials) else {
+        return;
+    };
+    visualized.retain(|entity| parts.contains(*entity));
+
+    let [headset, controller, joint] = assets.get_or_insert_with(|| {
+        [
+            (
+                meshes.add(shape::Box::new(0.18, 0.1, 0.1).into()),
+                materials.add(Color::GRAY.into()),
+            ),
+            (
+                meshes.add(shape::Box::new(0.04, 0.04, 0.12).into()),
+                materials.add(Color::ORANGE.into()),
+            ),
+            (
+                meshes.add(
+                    shape::UVSphere {
+                        radius: 0.008,
+                        ..default()
+                    }
+                    .into(),
+                ),
+                materials.add(Color::WHITE.into()),
+            ),
+        ]
+    });
+
+    for (entity, is_headset, is_controller) in parts.iter() {
+        if !visualized.insert(entity) {
+            continue;
+        }
+        let (mesh, material) = match (is_headset, is_controller) {
+            (true, _) => headset.clone(),
+            (_, true) => controller.clone(),
+            _ => joint.clone(),
+        };
+        commands.entity(entity).with_children(|part| {
+            part.spawn((
+                Name::new("XrRigVisual"),
+                PbrBundle {
+                    mesh,
+                    material,
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                RenderLayers::layer(settings.rig_layer),
+                XrRigVisual,
+            ));
+        });
+    }
+}
+
+/// Shows the [`XrRigVisual`]s of active parts if the [`XrSpectatorSettings::show_rig`] is set.
+pub fn update_rig_visuals(
+    settings: Res<XrSpectatorSettings>,
+    mut visuals: Query<(&Parent, &mut Visibility, &mut RenderLayers), With<XrRigVisual>>,
+    parts: Query<&XrActive>,
+) {
+    for (parent, mut visibility, mut render_layers) in visuals.iter_mut() {
+        let active = parts.get(parent.get()).is_ok_and(|active| active.0);
+        let shown = match settings.show_rig && active {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        };
+        visibility.set_if_neq(shown);
+        render_layers.set_if_neq(RenderLayers::layer(settings.rig_layer));
+    }
+}
@@ -1,4 +1,4 @@
-use bevy::{ecs::query::QuerySingleError, prelude::*};
+use bevy::{ecs::query::QuerySingleError, prelude::*, utils::HashMap};
 
 use crate::{
     controller::XrController,
@@ -9,7 +9,8 @@ use crate::{
         hand_joint::{Palm, Wrist},
         Hand, HandJointBundle, HandJointRadius,
     },
-    XrActive, XrLocal,
+    head::{XrEye, XrHead, XrHeadset, XrNeckModel},
+    XrActive, XrLocal, XrTrackingChanged, XrTrackingState,
 };
 
 pub fn draw_hand_gizmos(
@@ -27,7 +28,8 @@ pub fn draw_hand_gizmos(
 
         let (scale, _rotation, translation) = transform.to_scale_rotation_translation();
 
-        let radius = radius * scale.length();
+        // The radius is in meters of the tracking space, so it grows with the world scale of the origin.
+        let radius = radius * scale.max_element();
 
         gizmos.circle(translation, transform.forward(), radius, Color::WHITE);
         gizmos.line(
@@ -54,7 +56,7 @@ pub fn draw_controller_gizmos(
 ) {
     for transform in controller.iter() {
         let (scale, _, translation) = transform.to_scale_rotation_translation();
-        let radius = 0.05 * scale.length();
+        let radius = 0.05 * scale.max_element();
         gizmos.line(
             translation,
             translation + transform.forward() * radius,
@@ -76,16 +78,16 @@ pub fn draw_controller_gizmos(
 /// TODO: Make rotation average of Metacarpals. Tweak position by filtering the joints further.
 pub fn substitute_local_palm<Handedness: HandednessMarker>(
     mut palm: Query<
-        (Entity, &mut Transform, &mut XrActive),
+        (Entity, &mut Transform, &mut XrTrackingState),
         (With<XrLocal>, With<Handedness>, With<Palm>),
     >,
 
     wrist: Query<
-        (Entity, &GlobalTransform, &XrActive),
+        (Entity, &GlobalTransform, &XrTrackingState),
         (With<XrLocal>, With<Handedness>, With<Wrist>, Without<Palm>),
     >,
     joints: Query<
-        (&GlobalTransform, &XrActive),
+        (&GlobalTransform, &XrTrackingState),
         (
             With<XrLocal>,
             With<Handedness>,
@@ -98,8 +100,11 @@ pub fn substitute_local_palm<Handedness: HandednessMarker>(
 
     mut commands: Commands,
 ) {
-    if let Ok((wrist, wrist_transform, wrist_active)) = wrist.get_single() {
-        if wrist_active.0 & !joints.is_empty() & joints.iter().all(|(_, active)| active.0) {
+    if let Ok((wrist, wrist_transform, wrist_state)) = wrist.get_single() {
+        if wrist_state.is_active()
+            & !joints.is_empty()
+            & joints.iter().all(|(_, state)| state.is_active())
+        {
             // Calculate average for palm transform:
 
             let count = joints.iter().count() as f32;
@@ -118,10 +123,10 @@ pub fn substitute_local_palm<Handedness: HandednessMarker>(
             // Update or spawn palm entity.
 
             match palm.get_single_mut() {
-                Ok((_, mut transform, mut active)) => {
+                Ok((_, mut transform, mut state)) => {
                     transform.translation = translation;
                     transform.rotation = rotation;
-                    active.0 = true;
+                    state.set_if_neq(XrTrackingState::Tracked);
                 }
                 Err(QuerySingleError::MultipleEntities(_)) => {
                     let mut palms = palm.iter();
@@ -145,10 +150,290 @@ pub fn substitute_local_palm<Handedness: HandednessMarker>(
                     commands.entity(wrist).add_child(entity);
                 }
             }
+            return;
         }
     }
 
-    for (_, _, mut active) in palm.iter_mut() {
-        active.0 = false;
+    // The palm keeps its last pose while the joints are not tracked.
+    for (_, _, mut state) in palm.iter_mut() {
+        if state.is_active() {
+            *state = XrTrackingState::Lost;
+        }
+    }
+}
+
+/// Derives the [`XrHead`] bone from every local [`XrHeadset`] with a [`XrNeckModel`] and spawns the head if the rig has none.
+/// A spawned head is despawned again when its headset loses the neck model or is despawned.
+///
+/// For rotation only devices the position of the headset is synthesized as well and the [`XrEye`]s beside the headset keep their pose relative to it.
+/// The poses reported for the headset and the eyes are remembered, so frames in which they are not reported again do not move them further.
+#[allow(clippy::type_complexity)]
+pub fn apply_neck_models(
+    mut headsets: Query<
+        (
+            Entity,
+            &Parent,
+            &XrNeckModel,
+            &mut Transform,
+            &XrTrackingState,
+        ),
+        (With<XrHeadset>, With<XrLocal>, Without<XrHead>),
+    >,
+    mut heads: Query<
+        (&Parent, &mut Transform, &mut XrTrackingState),
+        (With<XrHead>, With<XrLocal>, Without<XrHeadset>),
+    >,
+    mut eyes: Query<
+        (Entity, &Parent, &mut Transform),
+        (
+            With<XrEye>,
+            With<XrLocal>,
+            Without<XrHeadset>,
+            Without<XrHead>,
+        ),
+    >,
+    mut spawned_heads: Local<HashMap<Entity, Entity>>,
+    mut reported_headsets: Local<HashMap<Entity, (Transform, Transform)>>,
+    mut eyes_from_headsets: Local<HashMap<Entity, (Transform, Transform)>>,
+    mut commands: Commands,
+) {
+    spawned_heads.retain(|headset, head| {
+        if headsets.contains(*headset) {
+            // Forget the heads which were despawned by the app.
+            return heads.contains(*head);
+        }
+        if let Some(head) = commands.get_entity(*head) {
+            head.despawn_recursive();
+        }
+        false
+    });
+
+    // The reported poses paired with the poses written by this system, a transform which differs from the written one was reported again.
+    reported_headsets.retain(|headset, _| {
+        headsets
+            .get(*headset)
+            .is_ok_and(|(_, _, neck_model, _, _)| neck_model.rotation_only)
+    });
+    eyes_from_headsets.retain(|eye, _| eyes.contains(*eye));
+
+    for (entity, origin, neck_model, mut headset_transform, headset_state) in headsets.iter_mut() {
+        if neck_model.rotation_only {
+            let reported = match reported_headsets.get(&entity) {
+                Some((reported, written)) if *written == *headset_transform => *reported,
+                _ => *headset_transform,
+            };
+            let synthesized = neck_model.headset_transform(&reported);
+            headset_transform.set_if_neq(synthesized);
+            reported_headsets.insert(entity, (reported, synthesized));
+
+            // Eyes parented to the headset follow it anyway.
+            for (eye, _, mut eye_transform) in eyes
+                .iter_mut()
+                .filter(|(_, parent, _)| parent.get() == origin.get())
+            {
+                let eye_from_headset = match eyes_from_headsets.get(&eye) {
+                    Some((eye_from_headset, written)) if *written == *eye_transform => {
+                        *eye_from_headset
+                    }
+                    _ => GlobalTransform::from(*eye_transform)
+                        .reparented_to(&GlobalTransform::from(reported)),
+                };
+                let synthesized_eye = synthesized.mul_transform(eye_from_headset);
+                eye_transform.set_if_neq(synthesized_eye);
+                eyes_from_headsets.insert(eye, (eye_from_headset, synthesized_eye));
+            }
+        }
+        let head_transform = neck_model.head_transform(&headset_transform);
+
+        match heads
+            .iter_mut()
+            .find(|(parent, _, _)| parent.get() == origin.get())
+        {
+            Some((_, mut transform, mut state)) => {
+                transform.set_if_neq(head_transform);
+                state.set_if_neq(*headset_state);
+            }
+            None => {
+                let head = commands
+                    .spawn((
+                        Name::new("XrHead"),
+                        SpatialBundle::from_transform(head_transform),
+                        XrHead,
+                        XrLocal,
+                        XrActive::from(*headset_state),
+                        *headset_state,
+                    ))
+                    .id();
+                commands.entity(origin.get()).add_child(head);
+                spawned_heads.insert(entity, head);
+            }
+        }
+    }
+}
+
+/// Sends a [`XrTrackingChanged`] event for every entity whose [`XrTrackingState`] transitioned to another state.
+pub fn send_tracking_changed(
+    states: Query<(Entity, &XrTrackingState), Changed<XrTrackingState>>,
+    mut removed: RemovedComponents<XrTrackingState>,
+    mut previous_states: Local<HashMap<Entity, XrTrackingState>>,
+    mut tracking_changed: EventWriter<XrTrackingChanged>,
+) {
+    for entity in removed.read() {
+        previous_states.remove(&entity);
+    }
+
+    for (entity, state) in states.iter() {
+        if let Some(previous) = previous_states.insert(entity, *state) {
+            if previous != *state {
+                tracking_changed.send(XrTrackingChanged {
+                    entity,
+                    previous,
+                    current: *state,
+                });
+            }
+        }
+    }
+}
+
+/// Derives the [`XrActive`] component from the [`XrTrackingState`].
+pub fn update_active(
+    mut active: Query<(&XrTrackingState, &mut XrActive), Changed<XrTrackingState>>,
+) {
+    for (state, mut active) in active.iter_mut() {
+        active.set_if_neq((*state).into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, HierarchyPlugin))
+            .add_systems(Update, apply_neck_models);
+        let origin = app.world.spawn(SpatialBundle::default()).id();
+        (app, origin)
+    }
+
+    fn spawn_headset(
+        app: &mut App,
+        origin: Entity,
+        neck_model: XrNeckModel,
+        transform: Transform,
+    ) -> Entity {
+        app.world
+            .spawn((
+                XrHeadset,
+                XrLocal,
+                neck_model,
+                XrTrackingState::Tracked,
+                SpatialBundle::from_transform(transform),
+            ))
+            .set_parent(origin)
+            .id()
+    }
+
+    fn head_of(app: &mut App, origin: Entity) -> Option<(Entity, Transform)> {
+        app.world
+            .query_filtered::<(Entity, &Parent, &Transform), With<XrHead>>()
+            .iter(&app.world)
+            .find(|(_, parent, _)| parent.get() == origin)
+            .map(|(head, _, transform)| (head, *transform))
+    }
+
+    #[test]
+    fn head_derived_from_headset() {
+        let (mut app, origin) = app();
+        let neck_model = XrNeckModel::default();
+        let rotation = Quat::from_euler(EulerRot::YXZ, 0.6, -0.3, 0.0);
+        let headset = spawn_headset(
+            &mut app,
+            origin,
+            neck_model,
+            Transform::from_xyz(0.2, 1.7, -0.4).with_rotation(rotation),
+        );
+        app.update();
+
+        let (head, transform) = head_of(&mut app, origin).unwrap();
+        assert!(transform.translation.abs_diff_eq(
+            Vec3::new(0.2, 1.7, -0.4) - rotation * neck_model.headset_offset,
+            1e-6
+        ));
+        assert_eq!(transform.rotation, rotation);
+        // The headset of a device with position tracking is not moved.
+        assert_eq!(
+            app.world.get::<Transform>(headset).unwrap().translation,
+            Vec3::new(0.2, 1.7, -0.4)
+        );
+
+        // The spawned head is despawned with the neck model.
+        app.world.entity_mut(headset).remove::<XrNeckModel>();
+        app.update();
+        assert!(app.world.get_entity(head).is_none());
+        assert!(head_of(&mut app, origin).is_none());
+    }
+
+    #[test]
+    fn rotation_only_headset_and_eyes_stay_fixed() {
+        let (mut app, origin) = app();
+        let neck_model = XrNeckModel::rotation_only(1.2);
+        let rotation = Quat::from_euler(EulerRot::YXZ, -0.4, 0.2, 0.0);
+        let headset = spawn_headset(
+            &mut app,
+            origin,
+            neck_model,
+            Transform::from_rotation(rotation),
+        );
+        let eyes = [-0.032, 0.032].map(|x| {
+            app.world
+                .spawn((
+                    XrEye(0),
+                    XrLocal,
+                    SpatialBundle::from_transform(
+                        Transform::from_rotation(rotation).with_translation(rotation * Vec3::X * x),
+                    ),
+                ))
+                .set_parent(origin)
+                .id()
+        });
+
+        let mut poses = None;
+        for _ in 0..5 {
+            // Like a runtime the headset is reported every frame, the eyes only once.
+            *app.world.get_mut::<Transform>(headset).unwrap() = Transform::from_rotation(rotation);
+            app.update();
+            let current = eyes.map(|eye| *app.world.get::<Transform>(eye).unwrap());
+            assert_eq!(*poses.get_or_insert(current), current);
+        }
+
+        let headset_transform = *app.world.get::<Transform>(headset).unwrap();
+        assert_eq!(
+            headset_transform,
+            neck_model.headset_transform(&Transform::from_rotation(rotation))
+        );
+        assert!((headset_transform.translation - Vec3::Y * 1.2)
+            .abs_diff_eq(rotation * neck_model.headset_offset, 1e-6));
+        let (_, head) = head_of(&mut app, origin).unwrap();
+        assert_eq!(head.translation, Vec3::Y * 1.2);
+        for (eye, x) in eyes.into_iter().zip([-0.032, 0.032]) {
+            let eye = app.world.get::<Transform>(eye).unwrap();
+            assert!(eye
+                .translation
+                .abs_diff_eq(headset_transform.translation + rotation * Vec3::X * x, 1e-6));
+        }
+
+        // The eyes turn with the headset.
+        let turned = Quat::from_rotation_y(1.0);
+        *app.world.get_mut::<Transform>(headset).unwrap() = Transform::from_rotation(turned);
+        app.update();
+        let headset_transform = *app.world.get::<Transform>(headset).unwrap();
+        for (eye, x) in eyes.into_iter().zip([-0.032, 0.032]) {
+            let eye = app.world.get::<Transform>(eye).unwrap();
+            assert!(eye
+                .translation
+                .abs_diff_eq(headset_transform.translation + turned * Vec3::X * x, 1e-5));
+            assert!(eye.rotation.abs_diff_eq(turned, 1e-5));
+        }
     }
 }
@@ -0,0 +1,584 @@
+//! Teleportation along a parabolic arc projected from a [`XrPointer`].
+//!
+//! While the [`XrTeleportTrigger`] of a pointer is held, the [`XrTeleportPlugin`] projects an arc from the pointer and stores it in a [`XrTeleportArc`] on the pointer entity.
+//! The arc lands on the entities marked with a [`XrTeleportTarget`], which are hit by their [`Aabb`], e.g. the one computed for every [`Mesh`].
+//! A target is valid if the slope of the hit surface does not exceed the [`XrTeleportSettings::max_slope`].
+//!
+//! Releasing the trigger over a valid target moves the [`XrOrigin`] of the pointer so the [`XrHead`] lands on the target, keeping the height of the head above the floor.
+//! The floor is at the origin, except for a [`XrOrigin::View`] at the head, which is [`XrSpaceSettings::view_height`] above the floor.
+//! The offset is computed in world space and converted into the space of the parent of the origin, then a [`XrTeleported`] event is sent.
+//!
+//! The arc and the target are previewed with [`Gizmos`], which requires the [`bevy::gizmos::GizmoPlugin`] unless [`XrTeleportSettings::preview`] is disabled.
+
+use bevy::{prelude::*, render::primitives::Aabb};
+
+use crate::{
+    controller::XrController,
+    controller_input::{DigitalInput, XrControllerInputType, XrControllerPress},
+    handedness::Handedness,
+    hands::Hand,
+    head::{XrHead, XrHeadset},
+    pointer::XrPointer,
+    space::{XrOrigin, XrSpaceSettings, XrWorldScale},
+    XrActive, XrLocal, XrSet,
+};
+
+pub struct XrTeleportPlugin;
+
+impl Plugin for XrTeleportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrTeleportSettings>()
+            .add_event::<XrTeleported>()
+            .add_systems(Update, aim_teleport.in_set(XrSet::Interaction))
+            .add_systems(
+                Update,
+                draw_teleport_preview
+                    .after(aim_teleport)
+                    .run_if(|settings: Res<XrTeleportSettings>| settings.preview),
+            );
+
+        app.register_type::<XrTeleportSettings>()
+            .register_type::<XrTeleportTrigger>()
+            .register_type::<XrTeleportTarget>()
+            .register_type::<XrTeleportArc>()
+            .register_type::<XrTeleportHit>()
+            .register_type::<XrTeleported>();
+    }
+}
+
+/// The input that aims while held and teleports on release.
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum XrTeleportTrigger {
+    /// A press of the [`XrController`] of the pointer.
+    Press(XrControllerInputType),
+    /// Pinching the thumb and the index tip of the hand of the pointer closer than the distance in meters of the tracking space.
+    Pinch(f32),
+}
+
+/// Settings of the [`XrTeleportPlugin`].
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Debug, Default)]
+pub struct XrTeleportSettings {
+    /// The controller pointers aim with this trigger.
+    pub controller_trigger: XrTeleportTrigger,
+    /// The hand pointers aim with this trigger.
+    pub hand_trigger: XrTeleportTrigger,
+    /// The initial speed along the pointer in meters of the tracking space per second, see [`XrWorldScale`].
+    pub speed: f32,
+    /// The downward acceleration of the arc in meters of the tracking space per second squared.
+    pub gravity: f32,
+    /// The time of flight at which the arc ends in seconds.
+    pub max_time: f32,
+    /// The amount of straight segments of the arc.
+    pub segments: usize,
+    /// The maximum angle between the surface normal and up in radians.
+    pub max_slope: f32,
+    /// Draws the arc and the target with [`Gizmos`].
+    pub preview: bool,
+}
+
+impl Default for XrTeleportSettings {
+    fn default() -> Self {
+        Self {
+            controller_trigger: XrTeleportTrigger::Press(XrControllerInputType::Stick),
+            hand_trigger: XrTeleportTrigger::Pinch(0.02),
+            speed: 7.0,
+            gravity: 9.81,
+            max_time: 2.0,
+            segments: 32,
+            max_slope: 30f32.to_radians(),
+            preview: true,
+        }
+    }
+}
+
+/// Marks the entities that can be teleported onto. The entity is hit by its [`Aabb`].
+#[derive(Component, Reflect, Default)]
+#[reflect(Component, Default)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct XrTeleportTarget;
+
+/// The point where a [`XrTeleportArc`] hit a [`XrTeleportTarget`].
+#[derive(Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, PartialEq)]
+pub struct XrTeleportHit {
+    pub entity: Entity,
+    pub point: Vec3,
+    pub normal: Vec3,
+    /// Is the slope of the surface within the [`XrTeleportSettings::max_slope`]?
+    pub valid: bool,
+}
+
+/// The arc of a [`XrPointer`] which is aiming, inserted and removed by the [`XrTeleportPlugin`].
+#[derive(Component, Debug, Default, Clone, PartialEq, Reflect)]
+#[reflect(Component, Debug, Default, PartialEq)]
+pub struct XrTeleportArc {
+    /// The points of the arc in world space up to the hit.
+    pub points: Vec<Vec3>,
+    pub hit: Option<XrTeleportHit>,
+}
+
+/// An [`Event`] sent after a [`XrOrigin`] was teleported.
+#[derive(Event, Debug, Copy, Clone, PartialEq, Reflect)]
+#[reflect(Debug, PartialEq)]
+pub struct XrTeleported {
+    pub origin: Entity,
+    pub pointer: Entity,
+    /// The translation of the origin relative to its parent before the teleport.
+    pub from: Vec3,
+    /// The translation of the origin relative to its parent after the teleport.
+    pub to: Vec3,
+}
+
+/// Projects the arc of a pointer and returns its points up to the first hit of a target.
+///
+/// The speed and the gravity of the `settings` are multiplied by the [`XrWorldScale`] of the origin of the pointer.
+pub fn project_teleport_arc(
+    settings: &XrTeleportSettings,
+    scale: f32,
+    start: Vec3,
+    direction: Vec3,
+    targets: &Query<(Entity, &GlobalTransform, &Aabb), With<XrTeleportTarget>>,
+) -> XrTeleportArc {
+    let mut arc = XrTeleportArc {
+        points: vec![start],
+        hit: None,
+    };
+    let velocity = direction.normalize_or_zero() * settings.speed * scale;
+    let gravity = settings.gravity * scale;
+    let segments = settings.segments.max(1);
+
+    for segment in 1..=segments {
+        let time = settings.max_time * segment as f32 / segments as f32;
+        let end = start + velocity * time + Vec3::NEG_Y * gravity * time * time / 2.0;
+        let previous = *arc.points.last().unwrap();
+
+        let hit = targets
+            .iter()
+            .filter_map(|(entity, transform, aabb)| {
+                intersect_segment(previous, end, transform, aabb)
+                    .map(|(t, normal)| (t, entity, normal))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some((t, entity, normal)) = hit {
+            let point = previous.lerp(end, t);
+            arc.points.push(point);
+            arc.hit = Some(XrTeleportHit {
+                entity,
+                point,
+                normal,
+                valid: normal.angle_between(Vec3::Y) <= settings.max_slope,
+            });
+            break;
+        }
+        arc.points.push(end);
+    }
+    arc
+}
+
+/// Intersects the segment with the oriented box and returns the fraction along the segment and the normal of the entered face.
+fn intersect_segment(
+    start: Vec3,
+    end: Vec3,
+    transform: &GlobalTransform,
+    aabb: &Aabb,
+) -> Option<(f32, Vec3)> {
+    let inverse = transform.affine().inverse();
+    let local_start = inverse.transform_point3(start) - Vec3::from(aabb.center);
+    let local_delta = inverse.transform_vector3(end - start);
+    let half_extents = Vec3::from(aabb.half_extents);
+
+    let mut enter = 0.0f32;
+    let mut exit = 1.0f32;
+    let mut normal = None;
+    for axis in 0..3 {
+        if local_delta[axis].abs() < f32::EPSILON {
+            if local_start[axis].abs() > half_extents[axis] {
+                return None;
+            }
+            continue;
+        }
+        let near = (-half_extents[axis] - local_start[axis]) / local_delta[axis];
+        let far = (half_extents[axis] - local_start[axis]) / local_delta[axis];
+        let (near, far) = if near < far { (near, far) } else { (far, near) };
+        if near > enter {
+            enter = near;
+            let mut axis_normal = Vec3::ZERO;
+            axis_normal[axis] = -local_delta[axis].signum();
+            normal = Some(axis_normal);
+        }
+        exit = exit.min(far);
+        if enter > exit {
+            return None;
+        }
+    }
+
+    // A segment starting inside of the box does not enter a face.
+    let normal = normal?;
+    let world_normal =
+        (transform.affine().matrix3.inverse().transpose() * normal).normalize_or_zero();
+    Some((enter, world_normal))
+}
+
+/// Returns the entity itself or the first ancestor with the component `T`.
+fn find_in_ancestors<'a, T: Component>(
+    entity: Entity,
+    components: &'a Query<&T>,
+    parents: &Query<&Parent>,
+) -> Option<(Entity, &'a T)> {
+    let mut current = entity;
+    loop {
+        if let Ok(component) = components.get(current) {
+            return Some((current, component));
+        }
+        current = parents.get(current).ok()?.get();
+    }
+}
+
+fn is_pinching(
+    handedness: Handedness,
+    distance: f32,
+    joints: &Query<(&Hand, &Handedness, &GlobalTransform, &XrActive), With<XrLocal>>,
+) -> bool {
+    let tip = |hand: Hand| {
+        joints
+            .iter()
+            .find(|(joint, joint_handedness, _, active)| {
+                **joint == hand && **joint_handedness == handedness && active.0
+            })
+            .map(|(_, _, transform, _)| transform.translation())
+    };
+    match (tip(Hand::ThumbTip), tip(Hand::IndexTip)) {
+        (Some(thumb), Some(index)) => thumb.distance(index) < distance,
+        _ => false,
+    }
+}
+
+/// Aims with the pointers whose trigger is held and teleports when the trigger is released over a valid target.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn aim_teleport(
+    settings: Res<XrTeleportSettings>,
+    space_settings: Res<XrSpaceSettings>,
+    presses: Res<DigitalInput<XrControllerPress>>,
+    mut commands: Commands,
+    pointers: Query<
+        (
+            Entity,
+            &XrPointer,
+            &GlobalTransform,
+            &XrActive,
+            Option<&XrTeleportArc>,
+        ),
+        With<XrLocal>,
+    >,
+    controllers: Query<&XrController>,
+    handedness: Query<&Handedness>,
+    parents: Query<&Parent>,
+    xr_origins: Query<&XrOrigin>,
+    world_scales: Query<&XrWorldScale>,
+    joints: Query<(&Hand, &Handedness, &GlobalTransform, &XrActive), With<XrLocal>>,
+    targets: Query<(Entity, &GlobalTransform, &Aabb), With<XrTeleportTarget>>,
+    heads: Query<
+        (Entity, &GlobalTransform, Has<XrHead>),
+        (Or<(With<XrHead>, With<XrHeadset>)>, With<XrLocal>),
+    >,
+    global_transforms: Query<&GlobalTransform>,
+    mut origins: Query<(&mut Transform, &GlobalTransform, Option<&Parent>), With<XrOrigin>>,
+    mut teleported_events: EventWriter<XrTeleported>,
+) {
+    for (entity, pointer, transform, active, arc) in pointers.iter() {
+        let scale = find_in_ancestors(entity, &world_scales, &parents)
+            .map_or(1.0, |(_, world_scale)| world_scale.0);
+        let trigger = match pointer {
+            XrPointer::Hand => settings.hand_trigger,
+            _ => settings.controller_trigger,
+        };
+        let held = active.0
+            && match trigger {
+                XrTeleportTrigger::Press(input_type) => {
+                    find_in_ancestors(entity, &controllers, &parents).is_some_and(
+                        |(_, xr_controller)| {
+                            presses.pressed(XrControllerPress::new(*xr_controller, input_type))
+                        },
+                    )
+                }
+                XrTeleportTrigger::Pinch(distance) => {
+                    find_in_ancestors(entity, &handedness, &parents).is_some_and(
+                        |(_, handedness)| is_pinching(*handedness, distance * scale, &joints),
+                    )
+                }
+            };
+
+        if held {
+            commands.entity(entity).insert(project_teleport_arc(
+                &settings,
+                scale,
+                transform.translation(),
+                transform.forward(),
+                &targets,
+            ));
+            continue;
+        }
+
+        let Some(arc) = arc else {
+            continue;
+        };
+        commands.entity(entity).remove::<XrTeleportArc>();
+        let Some(hit) = arc.hit.filter(|hit| hit.valid) else {
+            continue;
+        };
+        let Some((origin, xr_origin)) = find_in_ancestors(entity, &xr_origins, &parents) else {
+            continue;
+        };
+        // Prefer the head bone and fall back to the headset.
+        let Some((_, head, _)) = heads
+            .iter()
+            .filter(|(head, _, _)| {
+                find_in_ancestors(*head, &xr_origins, &parents)
+                    .is_some_and(|(head_origin, _)| head_origin == origin)
+            })
+            .max_by_key(|(_, _, is_head)| *is_head)
+        else {
+            continue;
+        };
+        let Ok((mut origin_transform, origin_global, origin_parent)) = origins.get_mut(origin)
+        else {
+            continue;
+        };
+
+        let head = head.translation();
+        let floor = match xr_origin {
+            XrOrigin::View => {
+                origin_global
+                    .transform_point(Vec3::NEG_Y * space_settings.view_height)
+                    .y
+            }
+            XrOrigin::Seat | XrOrigin::Room | XrOrigin::Other => origin_global.translation().y,
+        };
+        let offset = Vec3::new(
+            hit.point.x - head.x,
+            hit.point.y - floor,
+            hit.point.z - head.z,
+        );
+        // The offset is in world space, the translation of the origin is relative to its parent.
+        let translation = origin_global.translation() + offset;
+        let translation =
+            match origin_parent.and_then(|parent| global_transforms.get(parent.get()).ok()) {
+                Some(parent) => parent.affine().inverse().transform_point3(translation),
+                None => translation,
+            };
+        let from = origin_transform.translation;
+        origin_transform.translation = translation;
+
+        teleported_events.send(XrTeleported {
+            origin,
+            pointer: entity,
+            from,
+            to: translation,
+        });
+    }
+}
+
+/// Draws the [`XrTeleportArc`]s, green if they hit a valid target and red otherwise.
+pub fn draw_teleport_preview(arcs: Query<&XrTeleportArc>, mut gizmos: Gizmos) {
+    for arc in arcs.iter() {
+        let color = match arc.hit {
+            Some(hit) if hit.valid => Color::GREEN,
+            _ => Color::RED,
+        };
+        gizmos.linestrip(arc.points.iter().copied(), color);
+        if let Some(hit) = arc.hit {
+            gizmos.circle(hit.point, hit.normal, 0.25, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+
+    use super::*;
+
+    fn floor() -> (GlobalTransform, Aabb) {
+        (
+            GlobalTransform::IDENTITY,
+            Aabb::from_min_max(Vec3::new(-2.0, -0.1, -2.0), Vec3::new(2.0, 0.0, 2.0)),
+        )
+    }
+
+    #[test]
+    fn segment_hits_flat_box() {
+        let (transform, aabb) = floor();
+        let (t, normal) = intersect_segment(
+            Vec3::new(0.5, 1.0, 0.0),
+            Vec3::new(0.5, -1.0, 0.0),
+            &transform,
+            &aabb,
+        )
+        .unwrap();
+        assert!((t - 0.5).abs() < 1e-6);
+        assert!(normal.abs_diff_eq(Vec3::Y, 1e-6));
+
+        assert_eq!(
+            intersect_segment(
+                Vec3::new(3.0, 1.0, 0.0),
+                Vec3::new(3.0, -1.0, 0.0),
+                &transform,
+                &aabb
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn segment_starting_inside_box_misses() {
+        let (transform, aabb) = floor();
+        assert_eq!(
+            intersect_segment(
+                Vec3::new(0.0, -0.05, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                &transform,
+                &aabb
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn arc_rejects_steep_slope() {
+        let mut world = World::new();
+        let (_, aabb) = floor();
+        let flat = world
+            .spawn((XrTeleportTarget, GlobalTransform::IDENTITY, aabb))
+            .id();
+        let steep = world
+            .spawn((
+                XrTeleportTarget,
+                GlobalTransform::from(
+                    Transform::from_xyz(10.0, 0.0, 0.0)
+                        .with_rotation(Quat::from_rotation_z(45f32.to_radians())),
+                ),
+                aabb,
+            ))
+            .id();
+        let mut state = SystemState::<
+            Query<(Entity, &GlobalTransform, &Aabb), With<XrTeleportTarget>>,
+        >::new(&mut world);
+        let targets = state.get(&world);
+        let settings = XrTeleportSettings::default();
+
+        let arc = project_teleport_arc(
+            &settings,
+            1.0,
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            &targets,
+        );
+        let hit = arc.hit.unwrap();
+        assert_eq!(hit.entity, flat);
+        assert!(hit.valid);
+        assert_eq!(*arc.points.last().unwrap(), hit.point);
+
+        let arc = project_teleport_arc(
+            &settings,
+            1.0,
+            Vec3::new(10.0, 3.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            &targets,
+        );
+        let hit = arc.hit.unwrap();
+        assert_eq!(hit.entity, steep);
+        assert!(!hit.valid);
+    }
+
+    #[test]
+    fn arc_scales_with_world_scale() {
+        let mut world = World::new();
+        let mut state = SystemState::<
+            Query<(Entity, &GlobalTransform, &Aabb), With<XrTeleportTarget>>,
+        >::new(&mut world);
+        let targets = state.get(&world);
+        let settings = XrTeleportSettings::default();
+        let start = Vec3::new(1.0, 1.5, 0.0);
+        let direction = Vec3::new(0.0, 1.0, -1.0);
+
+        let arc = project_teleport_arc(&settings, 1.0, start, direction, &targets);
+        let scaled = project_teleport_arc(&settings, 2.0, start, direction, &targets);
+        assert_eq!(arc.points.len(), settings.segments + 1);
+        assert_eq!(scaled.points.len(), arc.points.len());
+        for (point, scaled) in arc.points.iter().zip(scaled.points.iter()) {
+            assert!(scaled.abs_diff_eq(start + (*point - start) * 2.0, 1e-4));
+        }
+    }
+
+    #[test]
+    fn teleport_in_parent_space() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, TransformPlugin, HierarchyPlugin))
+            .init_resource::<XrTeleportSettings>()
+            .init_resource::<XrSpaceSettings>()
+            .init_resource::<DigitalInput<XrControllerPress>>()
+            .add_event::<XrTeleported>()
+            .add_systems(Update, aim_teleport);
+
+        let parent = app
+            .world
+            .spawn(SpatialBundle::from_transform(
+                Transform::from_xyz(5.0, 1.0, -2.0)
+                    .with_rotation(Quat::from_rotation_y(1.0))
+                    .with_scale(Vec3::splat(2.0)),
+            ))
+            .id();
+        let origin = app
+            .world
+            .spawn((XrOrigin::Seat, SpatialBundle::default()))
+            .set_parent(parent)
+            .id();
+        let headset = app
+            .world
+            .spawn((
+                XrHeadset,
+                XrLocal,
+                SpatialBundle::from_transform(Transform::from_xyz(0.5, 1.6, 0.2)),
+            ))
+            .set_parent(origin)
+            .id();
+        let hit = XrTeleportHit {
+            entity: Entity::PLACEHOLDER,
+            point: Vec3::new(3.0, 0.5, 4.0),
+            normal: Vec3::Y,
+            valid: true,
+        };
+        app.world
+            .spawn((
+                XrPointer::Head,
+                XrLocal,
+                XrActive(true),
+                XrTeleportArc {
+                    points: vec![hit.point],
+                    hit: Some(hit),
+                },
+                SpatialBundle::default(),
+            ))
+            .set_parent(origin);
+        app.update();
+        app.update();
+
+        let headset = app.world.get::<GlobalTransform>(headset).unwrap();
+        let origin = app.world.get::<GlobalTransform>(origin).unwrap();
+        assert!(headset
+            .translation()
+            .abs_diff_eq(Vec3::new(3.0, 0.5 + 3.2, 4.0), 1e-4));
+        assert!((origin.translation().y - 0.5).abs() < 1e-4);
+    }
+}
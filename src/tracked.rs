@@ -3,13 +3,19 @@ use bevy::prelude::*;
 pub use crate::space::XrOrigin;
 pub use crate::XrActive;
 pub use crate::XrLocal;
+pub use crate::XrTrackingState;
 
 /// The defining [`Component`] for any tracked entities,
 /// Represents the transform of an object such as a headset, controller or puck.
 ///
 /// Tracked entities should be parented to a [`XrOrigin`] entity and include a [`XrActive`]..
-#[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
-#[reflect(Debug, Hash, PartialEq)]
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub struct XrTrackedObject(pub u8);
 
 #[derive(Bundle)]
@@ -18,6 +24,7 @@ pub struct XrTrackedObjectBundle {
     spatial_bundle: SpatialBundle,
     xr_local: XrLocal,
     xr_active: XrActive,
+    xr_tracking_state: XrTrackingState,
     xr_tracked_object: XrTrackedObject,
 }
 
@@ -28,23 +35,8 @@ impl XrTrackedObjectBundle {
             spatial_bundle: SpatialBundle::default(),
             xr_local: XrLocal,
             xr_active: XrActive(true),
+            xr_tracking_state: XrTrackingState::Tracked,
             xr_tracked_object: XrTrackedObject(index),
         }
     }
 }
-
-/// Ideas to support other things in the future that could be seperate from XrTrackedObject.
-#[cfg(notes)]
-mod notes {
-    /// Vuforia?
-    struct XrTrackedMesh;
-    struct XrTrackedImage;
-    struct XrTrackedMarker;
-    struct XrTrackedAnchor;
-    /// Apple?
-    struct XrTrackedFurniture;
-    /// ARCore? Apple?
-    struct XrTrackedWall;
-    struct XrTrackedWindow;
-    struct XrTrackedDoor;
-}
@@ -0,0 +1,447 @@
+//! Validation of the invariants of a local xr rig.
+//!
+//! The components of this crate document rules a xr platform specific crate should follow when spawning its entities.
+//! The opt-in [`XrValidationPlugin`] checks these rules whenever the rig changes and reports every new [`XrValidationIssue`] as an event and in the log.
+//!
+//! Backend authors can run [`xr_backend_conformance`] or [`assert_xr_conformance`] in their tests to check the hierarchy spawned by their [`XrBackend`].
+//!
+//! The checked rules are:
+//!
+//! - Every xr entity except the [`XrOrigin`] has a [`XrOrigin`] as ancestor.
+//! - Every xr entity has a [`XrLocal`], a [`XrActive`] and a [`XrTrackingState`].
+//! - Every [`XrEye`] has a [`Handedness`] and every index has exactly one left and one right eye.
+//! - Every [`XrView`] index is unique.
+//!
+//! Replicas of remote players are not part of the local rig and are not validated.
+
+use std::fmt;
+
+use bevy::{input::InputPlugin, prelude::*, utils::HashMap};
+
+use crate::{
+    backend::{XrBackend, XrRuntimePlugin},
+    controller::XrController,
+    handedness::Handedness,
+    hands::Hand,
+    head::{XrEye, XrHead, XrHeadset},
+    plugin::XrGizmoPlugin,
+    remote::{XrRemotePlayer, XrSnapshotPart},
+    space::XrOrigin,
+    tracked::XrTrackedObject,
+    window::XrWindow,
+    XrActive, XrLocal, XrPlugins, XrTrackingState, XrView,
+};
+
+/// Validates the local rig whenever it changes, see the [module docs](self).
+pub struct XrValidationPlugin;
+
+impl Plugin for XrValidationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<XrValidationSettings>()
+            .init_resource::<XrValidationReport>()
+            .add_event::<XrValidationIssue>()
+            .add_systems(Last, validate_local_rig.run_if(xr_rig_changed));
+
+        app.register_type::<XrValidationSettings>();
+    }
+}
+
+/// Settings of the [`XrValidationPlugin`].
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Debug, Default)]
+pub struct XrValidationSettings {
+    /// Validates the rig every frame instead of only when it changes.
+    pub every_frame: bool,
+    /// Logs every new issue as a warning or an error.
+    pub log: bool,
+}
+
+impl Default for XrValidationSettings {
+    fn default() -> Self {
+        Self {
+            every_frame: false,
+            log: true,
+        }
+    }
+}
+
+/// How severe a [`XrValidationIssue`] is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum XrValidationSeverity {
+    /// The rig works but some systems or apps might not find the entity.
+    Warning,
+    /// The rig breaks systems of this crate or the poses are wrong.
+    Error,
+}
+
+/// A violated rule of the local rig, see the [module docs](self).
+#[derive(Event, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum XrValidationIssue {
+    /// The entity has no [`XrOrigin`] as ancestor, so its pose is not relative to the tracking space.
+    NotParentedToOrigin { entity: Entity },
+    /// The entity has no [`XrActive`].
+    MissingActive { entity: Entity },
+    /// The entity has no [`XrLocal`].
+    MissingLocal { entity: Entity },
+    /// The entity has no [`XrTrackingState`], so its [`XrActive`] is never updated.
+    MissingTrackingState { entity: Entity },
+    /// The [`XrEye`] has no [`Handedness`].
+    MissingHandedness { entity: Entity },
+    /// The eyes with the index are not exactly one left and one right eye.
+    UnpairedEyes { index: u8, entities: Vec<Entity> },
+    /// Multiple entities share the [`XrView`] index.
+    DuplicateView { index: u8, entities: Vec<Entity> },
+}
+
+impl XrValidationIssue {
+    pub fn severity(&self) -> XrValidationSeverity {
+        match self {
+            XrValidationIssue::NotParentedToOrigin { .. }
+            | XrValidationIssue::MissingActive { .. }
+            | XrValidationIssue::DuplicateView { .. } => XrValidationSeverity::Error,
+            XrValidationIssue::MissingLocal { .. }
+            | XrValidationIssue::MissingTrackingState { .. }
+            | XrValidationIssue::MissingHandedness { .. }
+            | XrValidationIssue::UnpairedEyes { .. } => XrValidationSeverity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for XrValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XrValidationIssue::NotParentedToOrigin { entity } => {
+                write!(f, "{entity:?} is not a descendant of a XrOrigin")
+            }
+            XrValidationIssue::MissingActive { entity } => {
+                write!(f, "{entity:?} has no XrActive")
+            }
+            XrValidationIssue::MissingLocal { entity } => write!(f, "{entity:?} has no XrLocal"),
+            XrValidationIssue::MissingTrackingState { entity } => {
+                write!(f, "{entity:?} has no XrTrackingState")
+            }
+            XrValidationIssue::MissingHandedness { entity } => {
+                write!(f, "the XrEye {entity:?} has no Handedness")
+            }
+            XrValidationIssue::UnpairedEyes { index, entities } => write!(
+                f,
+                "the XrEyes {entities:?} with index {index} are not one left and one right eye"
+            ),
+            XrValidationIssue::DuplicateView { index, entities } => {
+                write!(
+                    f,
+                    "the entities {entities:?} share the XrView index {index}"
+                )
+            }
+        }
+    }
+}
+
+/// This [`Resource`] holds the issues found by the last validation of the [`XrValidationPlugin`].
+#[derive(Resource, Debug, Default, Clone)]
+pub struct XrValidationReport {
+    pub issues: Vec<XrValidationIssue>,
+}
+
+impl XrValidationReport {
+    /// Are there no issues of the [`XrValidationSeverity::Error`] severity?
+    pub fn is_valid(&self) -> bool {
+        self.issues
+            .iter()
+            .all(|issue| issue.severity() < XrValidationSeverity::Error)
+    }
+}
+
+/// The entities of the local rig.
+type XrEntityFilter = (
+    Or<(
+        With<XrOrigin>,
+        With<XrHeadset>,
+        With<XrHead>,
+        With<XrEye>,
+        With<XrWindow>,
+        With<XrView>,
+        With<XrController>,
+        With<XrTrackedObject>,
+        With<Hand>,
+    )>,
+    Without<XrRemotePlayer>,
+    Without<XrSnapshotPart>,
+);
+
+/// Returns all issues of the local rig in the `world`, see the [module docs](self).
+pub fn validate_rig(world: &mut World) -> Vec<XrValidationIssue> {
+    let mut issues = Vec::new();
+
+    let mut entities = world.query_filtered::<(
+        Entity,
+        Has<XrOrigin>,
+        Has<XrActive>,
+        Has<XrLocal>,
+        Has<XrTrackingState>,
+    ), XrEntityFilter>();
+    let mut parents = world.query::<&Parent>();
+    let mut origins = world.query_filtered::<(), With<XrOrigin>>();
+
+    for (entity, is_origin, has_active, has_local, has_tracking_state) in entities.iter(world) {
+        if !is_origin && !has_origin_ancestor(world, entity, &mut parents, &mut origins) {
+            issues.push(XrValidationIssue::NotParentedToOrigin { entity });
+        }
+        if !has_active {
+            issues.push(XrValidationIssue::MissingActive { entity });
+        }
+        if !has_local {
+            issues.push(XrValidationIssue::MissingLocal { entity });
+        }
+        if !has_tracking_state {
+            issues.push(XrValidationIssue::MissingTrackingState { entity });
+        }
+    }
+
+    let mut eyes: HashMap<u8, Vec<(Entity, Handedness)>> = HashMap::default();
+    let mut eye_query = world.query_filtered::<(Entity, &XrEye, Option<&Handedness>), (
+        Without<XrRemotePlayer>,
+        Without<XrSnapshotPart>,
+    )>();
+    for (entity, eye, handedness) in eye_query.iter(world) {
+        match handedness {
+            Some(handedness) => eyes.entry(eye.0).or_default().push((entity, *handedness)),
+            None => issues.push(XrValidationIssue::MissingHandedness { entity }),
+        }
+    }
+    for (index, pair) in eyes {
+        let count = |side| {
+            pair.iter()
+                .filter(|(_, handedness)| *handedness == side)
+                .count()
+        };
+        if count(Handedness::Left) != 1 || count(Handedness::Right) != 1 {
+            let mut entities: Vec<Entity> = pair.iter().map(|(entity, _)| *entity).collect();
+            entities.sort();
+            issues.push(XrValidationIssue::UnpairedEyes { index, entities });
+        }
+    }
+
+    let mut views: HashMap<u8, Vec<Entity>> = HashMap::default();
+    let mut view_query = world
+        .query_filtered::<(Entity, &XrView), (Without<XrRemotePlayer>, Without<XrSnapshotPart>)>();
+    for (entity, view) in view_query.iter(world) {
+        views.entry(view.0).or_default().push(entity);
+    }
+    for (index, mut entities) in views {
+        if entities.len() > 1 {
+            entities.sort();
+            issues.push(XrValidationIssue::DuplicateView { index, entities });
+        }
+    }
+
+    issues
+}
+
+fn has_origin_ancestor(
+    world: &World,
+    entity: Entity,
+    parents: &mut QueryState<&Parent>,
+    origins: &mut QueryState<(), With<XrOrigin>>,
+) -> bool {
+    let mut current = entity;
+    while let Ok(parent) = parents.get(world, current) {
+        current = parent.get();
+        if origins.get(world, current).is_ok() {
+            return true;
+        }
+    }
+    false
+}
+
+/// Run condition of the [`XrValidationPlugin`], true if the rig changed or [`XrValidationSettings::every_frame`] is enabled.
+#[allow(clippy::type_complexity)]
+pub fn xr_rig_changed(
+    settings: Res<XrValidationSettings>,
+    changed: Query<
+        (),
+        (
+            Or<(
+                Added<XrActive>,
+                Added<XrLocal>,
+                Added<XrTrackingState>,
+                Changed<Parent>,
+                Changed<XrEye>,
+                Changed<XrView>,
+                Changed<Handedness>,
+            )>,
+            XrEntityFilter,
+        ),
+    >,
+    removed_active: RemovedComponents<XrActive>,
+    removed_local: RemovedComponents<XrLocal>,
+    removed_tracking_state: RemovedComponents<XrTrackingState>,
+    removed_parent: RemovedComponents<Parent>,
+    removed_view: RemovedComponents<XrView>,
+) -> bool {
+    settings.every_frame
+        || !changed.is_empty()
+        || any_removed(removed_active)
+        || any_removed(removed_local)
+        || any_removed(removed_tracking_state)
+        || any_removed(removed_parent)
+        || any_removed(removed_view)
+}
+
+fn any_removed<T: Component>(mut removed: RemovedComponents<T>) -> bool {
+    removed.read().count() > 0
+}
+
+/// Validates the local rig, updates the [`XrValidationReport`] and reports the new issues.
+pub fn validate_local_rig(world: &mut World) {
+    let issues = validate_rig(world);
+    let log = world.resource::<XrValidationSettings>().log;
+
+    let mut report = world.resource_mut::<XrValidationReport>();
+    let new_issues: Vec<XrValidationIssue> = issues
+        .iter()
+        .filter(|issue| !report.issues.contains(issue))
+        .cloned()
+        .collect();
+    report.issues = issues;
+
+    for issue in new_issues.iter() {
+        if log {
+            match issue.severity() {
+                XrValidationSeverity::Warning => warn!("Xr validation: {issue}"),
+                XrValidationSeverity::Error => error!("Xr validation: {issue}"),
+            }
+        }
+    }
+    world.send_event_batch(new_issues);
+}
+
+/// Runs the `backend` in a headless app for the amount of `frames` and returns the issues of its rig.
+///
+/// Intended for the tests of xr platform specific crates.
+pub fn xr_backend_conformance(backend: impl XrBackend, frames: usize) -> Vec<XrValidationIssue> {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        InputPlugin,
+        TransformPlugin,
+        HierarchyPlugin,
+        XrPlugins.build().disable::<XrGizmoPlugin>(),
+        XrRuntimePlugin::new(backend),
+    ));
+    for _ in 0..frames.max(1) {
+        app.update();
+    }
+    validate_rig(&mut app.world)
+}
+
+/// Panics with a list of all issues if the local rig in the `world` breaks a rule of the [module docs](self).
+///
+/// Warnings are only accepted if `allow_warnings` is true.
+pub fn assert_xr_conformance(world: &mut World, allow_warnings: bool) {
+    let issues: Vec<String> = validate_rig(world)
+        .into_iter()
+        .filter(|issue| !allow_warnings || issue.severity() == XrValidationSeverity::Error)
+        .map(|issue| format!("- {issue}"))
+        .collect();
+    assert!(
+        issues.is_empty(),
+        "the xr rig does not conform:\n{}",
+        issues.join("\n")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::{XrBackendFrame, XrCapabilities, XrRigDescriptor, XrRuntime},
+        simulator::XrSimulatorBackend,
+    };
+
+    /// A backend spawning every kind of rig part without submitting poses.
+    struct FullRigBackend;
+
+    impl XrBackend for FullRigBackend {
+        fn name(&self) -> &str {
+            "full rig"
+        }
+
+        fn capabilities(&self) -> XrCapabilities {
+            XrCapabilities {
+                vr: true,
+                controllers: true,
+                hand_tracking: true,
+                ..default()
+            }
+        }
+
+        fn rig(&self) -> XrRigDescriptor {
+            XrRigDescriptor {
+                head: true,
+                eyes: 2,
+                windows: 2,
+                controllers: vec![
+                    XrController::Left,
+                    XrController::Right,
+                    XrController::Other(0),
+                ],
+                hands: true,
+                tracked_objects: 2,
+                ..default()
+            }
+        }
+
+        fn update(&mut self, _world: &World, _frame: &mut XrBackendFrame) {}
+    }
+
+    #[test]
+    fn simulator_conforms() {
+        assert_eq!(
+            xr_backend_conformance(XrSimulatorBackend::default(), 3),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn full_rig_conforms() {
+        assert_eq!(xr_backend_conformance(FullRigBackend, 3), vec![]);
+    }
+
+    #[test]
+    fn view_allocation() {
+        assert_eq!(XrView::eye(0, Handedness::Left), Some(XrView(0)));
+        assert_eq!(XrView::eye(1, Handedness::Right), Some(XrView(3)));
+        assert_eq!(XrView::eye(127, Handedness::Right), Some(XrView(255)));
+        assert_eq!(XrView::eye(128, Handedness::Left), None);
+        assert_eq!(XrView::window(2, 1), Some(XrView(5)));
+        assert_eq!(XrView::window(127, 2), None);
+        assert_eq!(XrView::window(128, 0), None);
+    }
+
+    #[test]
+    fn duplicate_view_is_reported() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            InputPlugin,
+            TransformPlugin,
+            HierarchyPlugin,
+            XrPlugins.build().disable::<XrGizmoPlugin>(),
+            XrRuntimePlugin::new(FullRigBackend),
+        ));
+        app.update();
+        let origin = app.world.resource::<XrRuntime>().origin().unwrap();
+        let view = app
+            .world
+            .spawn((XrView(0), XrLocal, XrActive(true), XrTrackingState::Tracked))
+            .set_parent(origin)
+            .id();
+
+        let issues = validate_rig(&mut app.world);
+        assert!(issues.iter().any(|issue| matches!(
+            issue,
+            XrValidationIssue::DuplicateView { index: 0, entities } if entities.contains(&view)
+        )));
+    }
+}
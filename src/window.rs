@@ -3,13 +3,22 @@ use bevy::prelude::*;
 pub use crate::space::XrOrigin;
 pub use crate::XrActive;
 pub use crate::XrLocal;
+pub use crate::XrTrackingState;
 pub use crate::XrView;
 
+use crate::projection::{XrFov, XrViewConfig};
+
 /// The defining [`Component`] for window entities,
 /// Represents the transform of a window such as a smartphone using webxr.
 ///
 /// See bundle for intended use.
-#[derive(Component)]
+#[derive(Component, Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component, Debug, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
 pub struct XrWindow(pub u8);
 
 #[derive(Bundle)]
@@ -18,18 +27,26 @@ pub struct XrWindowBundle {
     camera_bundle: Camera3dBundle,
     xr_local: XrLocal,
     xr_active: XrActive,
+    xr_tracking_state: XrTrackingState,
     xr_view: XrView,
+    xr_view_config: XrViewConfig,
     xr_window: XrWindow,
 }
 
 impl XrWindowBundle {
-    pub fn default(index: u8) -> Self {
+    /// The [`XrView`] of the window is allocated by the caller, e.g. with [`XrView::window`].
+    pub fn default(index: u8, view: XrView) -> Self {
         Self {
             name: Name::new("XrWindow_".to_string() + &index.to_string()),
             camera_bundle: Camera3dBundle::default(),
             xr_local: XrLocal,
             xr_active: XrActive(true),
-            xr_view: XrView(index),
+            xr_tracking_state: XrTrackingState::Tracked,
+            xr_view: view,
+            xr_view_config: XrViewConfig {
+                fov: XrFov::from_vertical(std::f32::consts::FRAC_PI_4, 1.0),
+                ..default()
+            },
             xr_window: XrWindow(index),
         }
     }